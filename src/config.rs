@@ -1,52 +1,331 @@
-use anyhow::{bail, Context, Result};
-use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::config_file::load_settings;
+use crate::media::default_extensions;
+use crate::metadata::DatePolicy;
+use crate::name_date::FilenameDates;
+use crate::plausible::PlausibleDates;
+use crate::provider::{build_provider, MetadataProvider};
+use crate::shift::ShiftRule;
+use crate::template::Template;
 
 // Constantes du projet
 pub const HASH_PREFIX_INITIAL_LEN: usize = 8;
 pub const HASH_PREFIX_MAX_LEN: usize = 20;
 pub const HASH_PREFIX_INCREMENT: usize = 4;
 pub const FILE_READ_BUFFER_SIZE: usize = 1024 * 1024; // 1 MiB
+pub const EXIFTOOL_MAX_RESTARTS: usize = 2;
+pub const EXIFTOOL_BATCH_SIZE: usize = 64;
+/// Délai de réponse d'exiftool pour une requête, au-delà duquel le processus est tué
+/// (secondes). Un lot hors délai est relu fichier par fichier, pour isoler le fichier
+/// qui bloque
+pub const EXIFTOOL_TIMEOUT_SECS: u64 = 120;
+pub const NAME_COUNTER_MAX: u32 = 9999;
+/// `mecla watch`: délai de stabilité d'un fichier, et d'inactivité avant nettoyage (secondes)
+pub const WATCH_SETTLE_SECS: u64 = 5;
+pub const WATCH_PRUNE_AFTER_SECS: u64 = 60;
+
+/// Templates par défaut: YYYY/MM[ TAG]/YYYY-MM-DD HH.MM.SS[.mmm].ext
+pub const DEFAULT_LAYOUT: &str = "{year}/{month}[ {tag}]";
+pub const DEFAULT_NAME: &str = "{year}-{month}-{day} {hour}.{minute}.{second}[.{subsec}]";
+
+/// Dossier de travail de mecla dans la bibliothèque (journaux, corbeille, index)
+pub const MECLA_DIR: &str = ".mecla";
+pub const JOURNAL_DIR: &str = "journal";
+pub const TRASH_DIR: &str = "trash";
+/// Index du contenu de la bibliothèque (hash BLAKE3 de chaque fichier), sous MECLA_DIR
+pub const INDEX_FILE: &str = "index.jsonl";
+/// Dossier de revue des quasi-doublons (`--near-dup review`), sous MECLA_DIR
+pub const REVIEW_DIR: &str = "review";
 
-/// Extensions par défaut supportées
-pub const DEFAULT_EXTENSIONS: &[&str] = &[
-    "jpg", "jpeg", "png", "heic", "gif", "tif", "tiff", // images
-    "mp4", "mov", "m4v", "avi", "mkv", "3gp", "mpo", // vidéos
+/// Arborescence des fichiers sans date de métadonnées, sous --output
+/// (`--on-missing-date unknown-dir`, `--undated-tree`)
+pub const UNDATED_DIR: &str = "Undated";
+/// Nom des fichiers sans date (nom d'origine), sous UNDATED_DIR ou en quarantaine
+pub const UNDATED_NAME: &str = "{stem}";
+/// Quarantaine des fichiers sans date (`--on-missing-date quarantine`), sous MECLA_DIR
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Dates écrites par une horloge non réglée (pile morte, remise à zéro): epochs
+/// QuickTime (1904), Unix (1970) et DOS (1980), date par défaut des appareils (2000).
+/// Seule la valeur exacte est rejetée: une photo prise le même jour reste valable
+pub const SENTINEL_DATES: &[&str] = &[
+    "1904-01-01 00:00:00",
+    "1970-01-01 00:00:00",
+    "1980-01-01 00:00:00",
+    "2000-01-01 00:00:00",
 ];
+/// Date de capture la plus ancienne acceptée par défaut (`--min-date`)
+pub const DEFAULT_MIN_DATE: &str = "1900-01-01";
+/// Avance tolérée sur l'heure courante (fuseaux horaires, horloges mal réglées)
+pub const FUTURE_DATE_TOLERANCE_HOURS: i64 = 24;
+
+/// Distance de Hamming max entre deux hashes perceptuels de quasi-doublons (sur 64 bits)
+pub const NEAR_DUP_MAX_DISTANCE: u32 = 6;
+
+/// Extensions des sidecars, classés avec le média de même nom mais jamais seuls
+/// (la moitié RAW d'une paire RAW+JPEG suit aussi son JPEG, voir `is_companion`)
+pub const SIDECAR_EXTENSIONS: &[&str] = &["xmp", "aae", "thm", "srt", "lrv"];
 
-#[derive(Copy, Clone, Debug, ValueEnum)]
+#[derive(Copy, Clone, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogMode {
     All,
     Conflicts,
     Errors,
 }
 
+/// Mode de transfert des fichiers vers la bibliothèque
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+    /// Déplace le fichier (la source disparaît)
+    Move,
+    /// Copie le fichier
+    Copy,
+    /// Crée un lien dur (même système de fichiers)
+    Hardlink,
+    /// Crée un lien symbolique vers la source
+    Symlink,
+    /// Clone copy-on-write (btrfs, XFS), copie classique sinon
+    Reflink,
+}
+
+impl TransferMode {
+    /// Indique si le mode modifie le dépôt (la source est retirée)
+    pub fn is_destructive(self) -> bool {
+        self == TransferMode::Move
+    }
+}
+
+/// Traitement des quasi-doublons (même image recompressée ou redimensionnée)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NearDupPolicy {
+    /// Pas de détection (pas de hash perceptuel)
+    Off,
+    /// Signale le quasi-doublon et classe le fichier normalement
+    Report,
+    /// Classe le fichier dans `.mecla/review` pour vérification
+    Review,
+    /// Laisse le fichier dans le dépôt
+    Skip,
+}
+
+/// Traitement des fichiers sans date de capture dans leurs métadonnées
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MissingDatePolicy {
+    /// Date de modification du fichier (souvent la date de copie)
+    Mtime,
+    /// Date contenue dans le nom du fichier, sinon comme `unknown-dir`
+    Filename,
+    /// Date contenue dans le nom d'un dossier du dépôt, sinon comme `unknown-dir`
+    Folder,
+    /// Laisse le fichier dans le dépôt
+    Skip,
+    /// Déplace le fichier dans `.mecla/quarantine` (nom d'origine)
+    Quarantine,
+    /// Classe le fichier sous `Undated/` (dossiers du dépôt et nom d'origine)
+    UnknownDir,
+}
+
+/// Format du rapport d'exécution (`--report`)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Tableau JSON, un objet par fichier
+    Json,
+    /// Une ligne par fichier, avec en-tête
+    Csv,
+}
+
+impl ReportFormat {
+    /// Format déduit de l'extension du fichier de rapport (JSON sauf `.csv`)
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("csv") => ReportFormat::Csv,
+            _ => ReportFormat::Json,
+        }
+    }
+}
+
+/// Backend de lecture des métadonnées
+#[derive(Copy, Clone, Debug, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataBackend {
+    /// Lecteur natif, exiftool en fallback (si disponible)
+    Auto,
+    /// exiftool uniquement
+    Exiftool,
+    /// Lecteur natif uniquement (sans exiftool)
+    Native,
+    /// Fixtures JSON en mémoire (voir --metadata-fixture)
+    Fixture,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "mecla")]
 #[command(
-    about = "Move media files from EXIF/metadata (via exiftool) to YYYY/MM or YYYY/MM <TAG>."
+    about = "Move media files from EXIF/metadata (native reader or exiftool) to YYYY/MM or YYYY/MM <TAG>."
 )]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Configuration file (TOML). Defaults to ~/.config/mecla/config.toml if it exists.
+    /// Command-line options override the file
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// Named profile of the configuration file ([profiles.<name>]), on top of [defaults]
+    #[arg(long)]
+    pub profile: Option<String>,
+
     /// Input directory (e.g., /path/_depot)
     #[arg(long)]
-    pub input: PathBuf,
+    pub input: Option<PathBuf>,
 
     /// Output directory (where to create YYYY/MM...)
     #[arg(long)]
-    pub output: PathBuf,
+    pub output: Option<PathBuf>,
 
     /// Do not modify anything, only display the actions
-    #[arg(long, default_value_t = false)]
+    #[arg(long, global = true, overrides_with = "no_dry_run")]
     pub dry_run: bool,
 
-    /// Log level: all, conflicts, errors
-    #[arg(long, value_enum, default_value_t = LogMode::Conflicts)]
-    pub log: LogMode,
+    /// Modify files even if the configuration file sets dry-run
+    #[arg(long, global = true, overrides_with = "dry_run")]
+    pub no_dry_run: bool,
+
+    /// Transfer mode: move (default), copy, hardlink, symlink, reflink.
+    /// Only move alters the input directory (duplicates trashed, tag dirs pruned).
+    #[arg(long, value_enum)]
+    pub mode: Option<TransferMode>,
+
+    /// Folder layout under --output. Placeholders: {year} {month} {day} {hour}
+    /// {minute} {second} {subsec} {subsec:N} {tag} {make} {model} {kind} {stem} {hash} {hash:N} {counter};
+    /// a [...] section is dropped when one of its placeholders is empty.
+    /// Default: "{year}/{month}[ {tag}]"
+    #[arg(long)]
+    pub layout: Option<String>,
+
+    /// File name template, without extension (same placeholders as --layout).
+    /// On a name collision, {counter} is incremented if used, otherwise a hash prefix is appended.
+    /// Default: "{year}-{month}-{day} {hour}.{minute}.{second}[.{subsec}]"
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Timezone (IANA name, e.g. Europe/Paris) used to convert UTC dates
+    /// (QuickTime videos, file mtime) to local time. Defaults to the system timezone
+    #[arg(long)]
+    pub timezone: Option<String>,
+
+    /// Camera clock correction, repeatable: "<camera model>=<offset>" or
+    /// "tag:<folder>=<offset>", e.g. --shift "Canon EOS 80D=+1h03m". First match wins
+    #[arg(long = "shift")]
+    pub shifts: Vec<String>,
+
+    /// Near-duplicate images (re-compressed or resized copies of a library photo):
+    /// off (default), report (log only), review (file under output/.mecla/review), skip (leave in input)
+    #[arg(long, value_enum)]
+    pub near_dup: Option<NearDupPolicy>,
+
+    /// Regex for dates in file names, repeatable, tried before the built-in patterns
+    /// (WhatsApp, Android, screenshots, Signal, Telegram, scanners) when metadata has
+    /// no date. Named groups: y, m, d, optional H, M, S.
+    /// Ex: --date-pattern "^DSC_(?P<y>\d{4})(?P<m>\d{2})(?P<d>\d{2})"
+    #[arg(long = "date-pattern")]
+    pub date_patterns: Vec<String>,
+
+    /// Oldest plausible capture date (YYYY-MM-DD). Dates before it, dates in the future
+    /// and unset camera clocks (exactly midnight on 1904-01-01, 1970-01-01, 1980-01-01 or
+    /// 2000-01-01) are ignored in favor of the next date tag. Default: 1900-01-01
+    #[arg(long)]
+    pub min_date: Option<String>,
+
+    /// Latest plausible capture date (YYYY-MM-DD). Default: today
+    #[arg(long)]
+    pub max_date: Option<String>,
+
+    /// Files without a capture date in their metadata or file name: mtime (default), filename
+    /// (any date-like part of the file name), folder (date in a folder name), skip (leave in input),
+    /// quarantine (output/.mecla/quarantine), unknown-dir (output/Undated).
+    /// filename and folder fall back to unknown-dir
+    #[arg(long, value_enum)]
+    pub on_missing_date: Option<MissingDatePolicy>,
+
+    /// Sort files whose date does not come from metadata (mtime, filename, folder)
+    /// under output/Undated instead of the main tree
+    #[arg(long, overrides_with = "no_undated_tree")]
+    pub undated_tree: bool,
+
+    /// Keep files dated without metadata in the main tree, even if the configuration
+    /// file sets undated-tree
+    #[arg(long, overrides_with = "undated_tree")]
+    pub no_undated_tree: bool,
+
+    /// Write a report with one record per input file (source, destination, action,
+    /// date and its source, hash, size, GPS position, error)
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+
+    /// Report format: json, csv. Defaults to csv for a .csv report file, json otherwise
+    #[arg(long, value_enum)]
+    pub report_format: Option<ReportFormat>,
+
+    /// Log level: all, conflicts (default), errors
+    #[arg(long, value_enum)]
+    pub log: Option<LogMode>,
 
     /// Extensions supported (optional). Ex: --ext jpg --ext mp4 ...
-    /// If not provided, a default set is used.
+    /// If not provided, all photo, RAW and video formats are used (audio is opt-in).
     #[arg(long = "ext")]
     pub exts: Vec<String>,
+
+    /// Metadata backend: auto (default: native reader, exiftool fallback), exiftool, native, fixture
+    #[arg(long, value_enum)]
+    pub metadata_backend: Option<MetadataBackend>,
+
+    /// JSON fixture for the fixture backend (`exiftool -json -G1 -n` format,
+    /// SourceFile relative to --input)
+    #[arg(long)]
+    pub metadata_fixture: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Restore the files of a previous run to their original locations
+    Undo {
+        /// Journal file of the run (output/.mecla/journal/<run>.jsonl)
+        journal: PathBuf,
+    },
+    /// Compute every action without touching any file, and write them to a plan file
+    /// for review (source hashes and sizes included)
+    Plan {
+        /// Plan file to write (JSON)
+        plan: PathBuf,
+    },
+    /// Execute exactly the actions of a plan file. Groups whose source changed since
+    /// planning, or whose destination is now taken, are refused
+    Apply {
+        /// Plan file written by `mecla plan`
+        plan: PathBuf,
+    },
+    /// Keep running and sort files as soon as they are dropped into --input
+    /// (existing files are sorted at startup)
+    Watch {
+        /// Seconds a file must stay unchanged (size and mtime) before it is sorted
+        #[arg(long, default_value_t = WATCH_SETTLE_SECS)]
+        settle: u64,
+        /// Seconds without activity before empty tag directories are pruned
+        #[arg(long, default_value_t = WATCH_PRUNE_AFTER_SECS)]
+        prune_after: u64,
+    },
 }
 
 #[derive(Debug)]
@@ -54,39 +333,153 @@ pub struct Config {
     pub input: PathBuf,
     pub output: PathBuf,
     pub dry_run: bool,
+    pub mode: TransferMode,
     #[allow(dead_code)]
     pub log: LogMode,
     pub exts: Vec<String>,
+    pub layout: Template,
+    pub name: Template,
+    /// Nom des fichiers sans date (`UNDATED_NAME`)
+    pub undated_name: Template,
+    /// Fuseau de conversion des dates UTC (None = fuseau du système)
+    pub timezone: Option<Tz>,
+    pub shifts: Vec<ShiftRule>,
+    pub near_dup: NearDupPolicy,
+    /// Filtre des dates invraisemblables (`--min-date`, `--max-date`, horloges non réglées)
+    pub plausible_dates: PlausibleDates,
+    /// Motifs de dates dans les noms de fichiers (après les métadonnées)
+    pub filename_dates: FilenameDates,
+    pub on_missing_date: MissingDatePolicy,
+    /// Classe sous `Undated/` les fichiers datés autrement que par leurs métadonnées
+    pub undated_tree: bool,
+    /// Rapport d'exécution (`--report`) et son format
+    pub report: Option<(PathBuf, ReportFormat)>,
+    pub metadata: Box<dyn MetadataProvider>,
 }
 
 impl Config {
-    /// Crée une configuration à partir des arguments CLI
-    pub fn from_args(args: Args) -> Result<Self> {
-        if args.input.as_os_str().is_empty() || args.output.as_os_str().is_empty() {
-            bail!("--input and --output are required");
+    /// Règles de datation des fichiers, empruntées à la configuration
+    pub fn date_policy(&self) -> DatePolicy<'_> {
+        DatePolicy {
+            timezone: self.timezone,
+            plausible: &self.plausible_dates,
+            filename_dates: &self.filename_dates,
+            on_missing_date: self.on_missing_date,
+            input: &self.input,
         }
+    }
+
+    /// Crée une configuration à partir des arguments CLI et du fichier de configuration.
+    ///
+    /// Une option passée en ligne de commande l'emporte sur celle du profil, qui
+    /// l'emporte sur celle de la section `[defaults]` du fichier.
+    pub fn from_args(args: Args) -> Result<Self> {
+        let file = load_settings(args.config.as_deref(), args.profile.as_deref())?;
 
-        let input = args
-            .input
+        let (input, output) = match (args.input.or(file.input), args.output.or(file.output)) {
+            (Some(i), Some(o)) if !i.as_os_str().is_empty() && !o.as_os_str().is_empty() => (i, o),
+            _ => bail!("--input and --output are required (command line or config file)"),
+        };
+
+        let input = input
             .canonicalize()
-            .with_context(|| format!("Unable to resolve --input: {:?}", args.input))?;
-
-        let exts = if args.exts.is_empty() {
-            log::info!(
-                "No extensions provided, using defaults: {:?}",
-                DEFAULT_EXTENSIONS
-            );
-            DEFAULT_EXTENSIONS.iter().map(|s| s.to_string()).collect()
-        } else {
-            normalize_exts(args.exts)
+            .with_context(|| format!("Unable to resolve --input: {:?}", input))?;
+
+        let exts = match (args.exts, file.ext) {
+            (exts, _) if !exts.is_empty() => normalize_exts(exts),
+            (_, Some(exts)) => normalize_exts(exts),
+            _ => {
+                let defaults = default_extensions();
+                log::info!("No extensions provided, using defaults: {:?}", defaults);
+                defaults
+            }
+        };
+
+        let layout = args.layout.or(file.layout);
+        let layout = Template::parse_layout(layout.as_deref().unwrap_or(DEFAULT_LAYOUT))
+            .context("Invalid --layout")?;
+        let name = args.name.or(file.name);
+        let name = Template::parse_name(name.as_deref().unwrap_or(DEFAULT_NAME))
+            .context("Invalid --name")?;
+        let undated_name = Template::parse_name(UNDATED_NAME)?;
+
+        let timezone = args.timezone.or(file.timezone);
+        let timezone = timezone
+            .as_deref()
+            .map(|tz| {
+                tz.parse::<Tz>()
+                    .map_err(|e| anyhow!("Invalid --timezone {:?}: {}", tz, e))
+            })
+            .transpose()?;
+
+        let shifts = match (args.shifts, file.shift) {
+            (shifts, Some(from_file)) if shifts.is_empty() => from_file,
+            (shifts, _) => shifts,
+        };
+        let shifts = shifts
+            .iter()
+            .map(|s| ShiftRule::parse(s))
+            .collect::<Result<Vec<_>>>()?;
+
+        let report = args.report.or(file.report).map(|path| {
+            let format = args
+                .report_format
+                .or(file.report_format)
+                .unwrap_or_else(|| ReportFormat::from_path(&path));
+            (path, format)
+        });
+
+        let date_patterns = match (args.date_patterns, file.date_pattern) {
+            (patterns, Some(from_file)) if patterns.is_empty() => from_file,
+            (patterns, _) => patterns,
         };
+        let filename_dates = FilenameDates::new(&date_patterns)?;
+
+        let plausible_dates = PlausibleDates::new(
+            args.min_date.or(file.min_date).as_deref(),
+            args.max_date.or(file.max_date).as_deref(),
+        )?;
+
+        let metadata_fixture = args.metadata_fixture.or(file.metadata_fixture);
+        let metadata = build_provider(
+            args.metadata_backend
+                .or(file.metadata_backend)
+                .unwrap_or(MetadataBackend::Auto),
+            metadata_fixture.as_deref(),
+            &input,
+            plausible_dates,
+        )?;
+        log::debug!("Using metadata backend: {}", metadata.name());
 
         let cfg = Config {
             input,
-            output: args.output,
-            dry_run: args.dry_run,
-            log: args.log,
+            output,
+            dry_run: flag(args.dry_run, args.no_dry_run)
+                .or(file.dry_run)
+                .unwrap_or(false),
+            mode: args.mode.or(file.mode).unwrap_or(TransferMode::Move),
+            log: args.log.or(file.log).unwrap_or(LogMode::Conflicts),
             exts,
+            layout,
+            name,
+            undated_name,
+            timezone,
+            shifts,
+            near_dup: args
+                .near_dup
+                .or(file.near_dup)
+                .unwrap_or(NearDupPolicy::Off),
+            plausible_dates,
+            filename_dates,
+            on_missing_date: args
+                .on_missing_date
+                .or(file.on_missing_date)
+                .unwrap_or(MissingDatePolicy::Mtime),
+            undated_tree: flag(args.undated_tree, args.no_undated_tree)
+                .or(file.undated_tree)
+                .unwrap_or(false),
+            report,
+            metadata,
         };
 
         cfg.validate()?;
@@ -115,6 +508,28 @@ impl Config {
     }
 }
 
+/// Indique si l'exécution est simulée, pour les commandes qui ne construisent pas de
+/// `Config` (`undo`, `apply`): ligne de commande, sinon fichier de configuration.
+///
+/// # Errors
+/// Retourne une erreur si le fichier de configuration ou le profil est invalide
+pub fn dry_run_setting(args: &Args) -> Result<bool> {
+    if let Some(dry_run) = flag(args.dry_run, args.no_dry_run) {
+        return Ok(dry_run);
+    }
+    let file = load_settings(args.config.as_deref(), args.profile.as_deref())?;
+    Ok(file.dry_run.unwrap_or(false))
+}
+
+/// Valeur d'une option `--x` / `--no-x` (None si aucune n'est passée)
+fn flag(yes: bool, no: bool) -> Option<bool> {
+    match (yes, no) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
 /// Normalise les extensions (minuscules, sans point)
 fn normalize_exts(mut exts: Vec<String>) -> Vec<String> {
     for e in &mut exts {
@@ -123,3 +538,62 @@ fn normalize_exts(mut exts: Vec<String>) -> Vec<String> {
     exts.retain(|e| !e.is_empty());
     exts
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn command_line_overrides_profile_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("input")).unwrap();
+        let config = dir.path().join("config.toml");
+        fs::write(
+            &config,
+            "[defaults]\ninput = \"input\"\noutput = \"output\"\nmetadata-backend = \"native\"\n\
+             [profiles.review]\ndry-run = true\nundated-tree = true\n",
+        )
+        .unwrap();
+
+        let from = |flags: &[&str]| {
+            let mut argv = vec!["mecla", "--config", config.to_str().unwrap()];
+            argv.extend(["--profile", "review"]);
+            argv.extend(flags);
+            Config::from_args(Args::parse_from(argv)).unwrap()
+        };
+
+        let cfg = from(&[]);
+        assert!(cfg.dry_run && cfg.undated_tree);
+
+        let cfg = from(&["--no-dry-run", "--no-undated-tree"]);
+        assert!(!cfg.dry_run && !cfg.undated_tree);
+
+        // La dernière des deux options l'emporte
+        let cfg = from(&["--no-dry-run", "--dry-run"]);
+        assert!(cfg.dry_run);
+    }
+
+    #[test]
+    fn undo_and_apply_read_dry_run_from_the_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config.toml");
+        fs::write(&config, "[profiles.review]\ndry-run = true\n").unwrap();
+
+        let dry_run = |flags: &[&str]| {
+            let mut argv = vec!["mecla", "--config", config.to_str().unwrap()];
+            argv.extend(flags);
+            dry_run_setting(&Args::parse_from(argv)).unwrap()
+        };
+        assert!(!dry_run(&["apply", "plan.json"]));
+        assert!(dry_run(&["--profile", "review", "apply", "plan.json"]));
+        assert!(dry_run(&["--profile", "review", "undo", "run.jsonl"]));
+        assert!(!dry_run(&[
+            "--profile",
+            "review",
+            "undo",
+            "run.jsonl",
+            "--no-dry-run"
+        ]));
+    }
+}
@@ -0,0 +1,283 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    LogMode, MetadataBackend, MissingDatePolicy, NearDupPolicy, ReportFormat, TransferMode,
+};
+
+/// Options d'exécution lues dans le fichier de configuration (section `[defaults]`
+/// ou `[profiles.<nom>]`). Les clés sont celles des options de la ligne de commande.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub dry_run: Option<bool>,
+    pub mode: Option<TransferMode>,
+    pub layout: Option<String>,
+    pub name: Option<String>,
+    pub timezone: Option<String>,
+    pub shift: Option<Vec<String>>,
+    pub near_dup: Option<NearDupPolicy>,
+    pub date_pattern: Option<Vec<String>>,
+    pub min_date: Option<String>,
+    pub max_date: Option<String>,
+    pub on_missing_date: Option<MissingDatePolicy>,
+    pub undated_tree: Option<bool>,
+    pub report: Option<PathBuf>,
+    pub report_format: Option<ReportFormat>,
+    pub log: Option<LogMode>,
+    pub ext: Option<Vec<String>>,
+    pub metadata_backend: Option<MetadataBackend>,
+    pub metadata_fixture: Option<PathBuf>,
+}
+
+impl Settings {
+    /// Complète ces options par celles de `base` (les valeurs déjà définies l'emportent)
+    fn or(self, base: Settings) -> Settings {
+        Settings {
+            input: self.input.or(base.input),
+            output: self.output.or(base.output),
+            dry_run: self.dry_run.or(base.dry_run),
+            mode: self.mode.or(base.mode),
+            layout: self.layout.or(base.layout),
+            name: self.name.or(base.name),
+            timezone: self.timezone.or(base.timezone),
+            shift: self.shift.or(base.shift),
+            near_dup: self.near_dup.or(base.near_dup),
+            date_pattern: self.date_pattern.or(base.date_pattern),
+            min_date: self.min_date.or(base.min_date),
+            max_date: self.max_date.or(base.max_date),
+            on_missing_date: self.on_missing_date.or(base.on_missing_date),
+            undated_tree: self.undated_tree.or(base.undated_tree),
+            report: self.report.or(base.report),
+            report_format: self.report_format.or(base.report_format),
+            log: self.log.or(base.log),
+            ext: self.ext.or(base.ext),
+            metadata_backend: self.metadata_backend.or(base.metadata_backend),
+            metadata_fixture: self.metadata_fixture.or(base.metadata_fixture),
+        }
+    }
+
+    /// Résout les chemins relatifs par rapport au dossier du fichier de configuration
+    fn resolve_paths(mut self, dir: &Path) -> Settings {
+        for path in [
+            &mut self.input,
+            &mut self.output,
+            &mut self.report,
+            &mut self.metadata_fixture,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = resolve_path(path, dir);
+        }
+        self
+    }
+}
+
+/// Fichier de configuration (`~/.config/mecla/config.toml` ou `--config`).
+///
+/// ```toml
+/// [defaults]
+/// output = "/mnt/nas/Photos"
+///
+/// [profiles.phone-import]
+/// input = "/media/phone/DCIM"
+/// mode = "copy"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    defaults: Settings,
+    #[serde(default)]
+    profiles: BTreeMap<String, Settings>,
+}
+
+/// Charge les options du fichier de configuration.
+///
+/// Sans `--config`, le fichier par défaut est utilisé s'il existe. Les options du
+/// profil demandé complètent celles de `[defaults]`.
+///
+/// # Arguments
+/// * `path` - Fichier passé avec `--config`
+/// * `profile` - Profil passé avec `--profile`
+///
+/// # Returns
+/// Les options du fichier (vides s'il n'y a pas de fichier)
+///
+/// # Errors
+/// Retourne une erreur si le fichier est illisible ou invalide (clé inconnue,
+/// valeur invalide), ou si le profil n'existe pas
+pub fn load_settings(path: Option<&Path>, profile: Option<&str>) -> Result<Settings> {
+    let path = match path {
+        Some(p) => Some(p.to_path_buf()),
+        None => default_config_path().filter(|p| p.is_file()),
+    };
+    read_settings(path.as_deref(), profile)
+}
+
+/// Lit les options d'un fichier de configuration (voir `load_settings`)
+///
+/// # Arguments
+/// * `path` - Fichier de configuration (None: aucun fichier)
+/// * `profile` - Profil passé avec `--profile`
+fn read_settings(path: Option<&Path>, profile: Option<&str>) -> Result<Settings> {
+    let Some(path) = path else {
+        if let Some(profile) = profile {
+            bail!(
+                "--profile {:?} requires a configuration file ({} or --config)",
+                profile,
+                default_config_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "~/.config/mecla/config.toml".to_string())
+            );
+        }
+        return Ok(Settings::default());
+    };
+
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Unable to read config file {}", path.display()))?;
+    let file: ConfigFile = toml::from_str(&text)
+        .map_err(|e| anyhow!("Invalid config file {}: {}", path.display(), e))?;
+    log::debug!("Using config file: {}", path.display());
+
+    let mut settings = file.defaults;
+    if let Some(name) = profile {
+        let Some(p) = file.profiles.get(name) else {
+            bail!(
+                "Unknown profile {:?} in {} (available: {})",
+                name,
+                path.display(),
+                if file.profiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    file.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+                }
+            );
+        };
+        settings = p.clone().or(settings);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    Ok(settings.resolve_paths(dir))
+}
+
+/// Fichier de configuration par défaut: `$XDG_CONFIG_HOME/mecla/config.toml`,
+/// sinon `~/.config/mecla/config.toml`
+fn default_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|h| h.join(".config")))?;
+    Some(base.join("mecla").join("config.toml"))
+}
+
+/// Dossier personnel de l'utilisateur
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Développe `~` et rend un chemin relatif au dossier du fichier de configuration
+fn resolve_path(path: &Path, dir: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Some(home) = home_dir() {
+            return home.join(rest);
+        }
+    }
+    if path.is_relative() {
+        dir.join(path)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Écrit un fichier de configuration et le lit avec le profil donné
+    fn load(toml: &str, profile: Option<&str>) -> (tempfile::TempDir, Result<Settings>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, toml).unwrap();
+        let settings = read_settings(Some(&path), profile);
+        (dir, settings)
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        for toml in [
+            "[defaults]\noutptu = \"/photos\"\n",
+            "[profiles.nas]\nmode = \"copy\"\ndryrun = true\n",
+            "[default]\noutput = \"/photos\"\n",
+        ] {
+            let (_dir, settings) = load(toml, None);
+            let err = settings.unwrap_err().to_string();
+            assert!(err.contains("Invalid config file"), "{}", err);
+            assert!(err.contains("unknown field"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn unknown_profile_lists_the_available_ones() {
+        let toml = "[profiles.phone-import]\n[profiles.nas-archive]\n";
+        let (_dir, settings) = load(toml, Some("phone"));
+        let err = settings.unwrap_err().to_string();
+        assert!(
+            err.contains("Unknown profile \"phone\"") && err.contains("nas-archive, phone-import"),
+            "{}",
+            err
+        );
+
+        let (_dir, settings) = load("", Some("phone"));
+        assert!(settings
+            .unwrap_err()
+            .to_string()
+            .contains("available: none"));
+    }
+
+    #[test]
+    fn profile_requires_a_config_file() {
+        let err = read_settings(None, Some("nas")).unwrap_err().to_string();
+        assert!(err.contains("requires a configuration file"), "{}", err);
+        assert!(read_settings(None, None).unwrap().output.is_none());
+    }
+
+    #[test]
+    fn profile_falls_back_to_defaults() {
+        let toml = "[defaults]\noutput = \"/photos\"\nmode = \"move\"\n\
+                    [profiles.phone-import]\nmode = \"copy\"\next = [\"heic\"]\n";
+        let (_dir, settings) = load(toml, Some("phone-import"));
+        let settings = settings.unwrap();
+        assert_eq!(settings.output.as_deref(), Some(Path::new("/photos")));
+        assert_eq!(settings.mode, Some(TransferMode::Copy));
+        assert_eq!(settings.ext, Some(vec!["heic".to_string()]));
+
+        // Sans profil: seulement [defaults]
+        let (_dir, settings) = load(toml, None);
+        let settings = settings.unwrap();
+        assert_eq!(settings.mode, Some(TransferMode::Move));
+        assert!(settings.ext.is_none());
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_config_file() {
+        let toml = "[defaults]\ninput = \"depot\"\noutput = \"/photos\"\n\
+                    report = \"~/mecla/report.json\"\n";
+        let (dir, settings) = load(toml, None);
+        let settings = settings.unwrap();
+        assert_eq!(settings.input, Some(dir.path().join("depot")));
+        assert_eq!(settings.output.as_deref(), Some(Path::new("/photos")));
+        if let Some(home) = home_dir() {
+            assert_eq!(settings.report, Some(home.join("mecla/report.json")));
+        }
+    }
+}
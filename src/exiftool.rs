@@ -0,0 +1,326 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::ffi::{OsStr, OsString};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{EXIFTOOL_MAX_RESTARTS, EXIFTOOL_TIMEOUT_SECS};
+
+/// Processus exiftool persistant, piloté via `-stay_open True -@ -`.
+///
+/// Chaque requête est envoyée sur stdin (un argument par ligne) et terminée
+/// par `-executeN`; exiftool répond sur stdout puis écrit `{readyN}`.
+/// stdout est lu par un thread dédié, ce qui permet d'abandonner une requête
+/// qui ne répond pas dans les délais.
+struct ExiftoolProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<Vec<u8>>,
+    seq: u64,
+    /// La dernière requête n'a pas répondu à temps (le processus a été tué)
+    timed_out: bool,
+}
+
+impl ExiftoolProcess {
+    /// Lance un nouveau processus exiftool en mode stay_open
+    fn spawn(program: &OsStr) -> Result<Self> {
+        let mut child = Command::new(program)
+            .arg("-stay_open")
+            .arg("True")
+            .arg("-@")
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Unable to start exiftool in stay_open mode")?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("exiftool stdin unavailable"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("exiftool stdout unavailable"))?;
+
+        // Le thread s'arrête de lui-même à la fermeture de stdout (fin ou kill du processus)
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let mut line = Vec::new();
+                match stdout.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            seq: 0,
+            timed_out: false,
+        })
+    }
+
+    /// Exécute une requête et retourne la sortie standard jusqu'au marqueur `{readyN}`.
+    ///
+    /// # Errors
+    /// Retourne une erreur si le processus ne répond plus (crash, pipe fermé,
+    /// délai `timeout` dépassé). Un processus hors délai est tué.
+    fn execute<S: AsRef<OsStr>>(&mut self, args: &[S], timeout: Duration) -> Result<String> {
+        self.seq += 1;
+        let ready = format!("{{ready{}}}", self.seq);
+
+        let mut request = Vec::new();
+        for a in args {
+            request.extend_from_slice(&os_str_bytes(a.as_ref()));
+            request.push(b'\n');
+        }
+        request.extend_from_slice(format!("-execute{}\n", self.seq).as_bytes());
+
+        self.stdin
+            .write_all(&request)
+            .and_then(|_| self.stdin.flush())
+            .context("write to exiftool stdin")?;
+
+        let deadline = Instant::now() + timeout;
+        let mut out = String::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(remaining) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Disconnected) => bail!("exiftool exited unexpectedly"),
+                Err(RecvTimeoutError::Timeout) => {
+                    self.timed_out = true;
+                    let _ = self.child.kill();
+                    bail!("exiftool did not answer within {}s", timeout.as_secs_f32());
+                }
+            };
+            let s = String::from_utf8_lossy(&line);
+            if s.trim_end() == ready {
+                return Ok(out);
+            }
+            out.push_str(&s);
+        }
+    }
+}
+
+impl Drop for ExiftoolProcess {
+    fn drop(&mut self) {
+        // Demande un arrêt propre, puis on s'assure que le processus est bien terminé.
+        let _ = self.stdin.write_all(b"-stay_open\nFalse\n");
+        let _ = self.stdin.flush();
+        if !matches!(self.child.try_wait(), Ok(Some(_))) {
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Pool de processus exiftool persistants partagé par les workers rayon.
+///
+/// Chaque thread rayon utilise son propre emplacement (indexé par
+/// `rayon::current_thread_index`), les processus sont lancés à la demande
+/// et relancés automatiquement s'ils meurent en cours de route.
+/// Un processus qui ne répond pas dans `EXIFTOOL_TIMEOUT_SECS` (fichier corrompu
+/// qui bloque exiftool) est tué, et la requête échoue sans être renvoyée: elle
+/// bloquerait de nouveau. Le processus est relancé pour la requête suivante.
+pub struct ExiftoolPool {
+    slots: Vec<Mutex<Option<ExiftoolProcess>>>,
+    program: OsString,
+    timeout: Duration,
+}
+
+impl ExiftoolPool {
+    /// Crée un pool de `size` emplacements (au moins un)
+    pub fn new(size: usize) -> Self {
+        Self::with_program(size, "exiftool", Duration::from_secs(EXIFTOOL_TIMEOUT_SECS))
+    }
+
+    /// Crée un pool qui lance `program` au lieu d'exiftool, avec un délai de réponse donné
+    pub(crate) fn with_program(
+        size: usize,
+        program: impl Into<OsString>,
+        timeout: Duration,
+    ) -> Self {
+        let slots = (0..size.max(1)).map(|_| Mutex::new(None)).collect();
+        Self {
+            slots,
+            program: program.into(),
+            timeout,
+        }
+    }
+
+    /// Exécute une requête exiftool sur un processus du pool.
+    ///
+    /// # Arguments
+    /// * `args` - Arguments exiftool (options puis fichiers), un par élément
+    ///
+    /// # Returns
+    /// La sortie standard d'exiftool pour cette requête
+    ///
+    /// # Errors
+    /// Retourne une erreur si un argument contient un saut de ligne, si exiftool
+    /// ne peut pas être lancé, s'il ne répond pas à temps, ou s'il plante plus de
+    /// `EXIFTOOL_MAX_RESTARTS` fois de suite
+    pub fn execute<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<String> {
+        // Un argument par ligne: un saut de ligne (nom de fichier piégé)
+        // injecterait des options supplémentaires, y compris d'écriture de tags.
+        if let Some(a) = args.iter().find(|a| has_line_break(a.as_ref())) {
+            bail!(
+                "refusing exiftool argument containing a line break: {:?}",
+                a.as_ref()
+            );
+        }
+
+        let idx = rayon::current_thread_index().unwrap_or(0) % self.slots.len();
+        let mut slot = self.slots[idx].lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut attempt = 0;
+        loop {
+            let process = match slot.as_mut() {
+                Some(p) => p,
+                None => slot.insert(ExiftoolProcess::spawn(&self.program)?),
+            };
+
+            match process.execute(args, self.timeout) {
+                Ok(out) => return Ok(out),
+                Err(e) => {
+                    // Le processus est dans un état inconnu: on le jette.
+                    let timed_out = process.timed_out;
+                    *slot = None;
+                    if timed_out {
+                        return Err(e);
+                    }
+                    if attempt >= EXIFTOOL_MAX_RESTARTS {
+                        return Err(e.context("exiftool keeps crashing"));
+                    }
+                    attempt += 1;
+                    log::warn!("exiftool worker crashed ({:#}), restarting", e);
+                }
+            }
+        }
+    }
+}
+
+/// Vrai si l'argument ne tient pas sur une ligne du fichier d'arguments d'exiftool
+pub fn has_line_break(s: &OsStr) -> bool {
+    os_str_bytes(s).iter().any(|b| matches!(b, b'\n' | b'\r'))
+}
+
+/// Représentation brute d'un argument pour le fichier d'arguments d'exiftool
+#[cfg(unix)]
+fn os_str_bytes(s: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    s.as_bytes().to_vec()
+}
+
+/// Représentation brute d'un argument pour le fichier d'arguments d'exiftool
+#[cfg(not(unix))]
+fn os_str_bytes(s: &OsStr) -> Vec<u8> {
+    s.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::{Path, PathBuf};
+
+    /// Faux exiftool: répond `ok` à chaque requête et s'arrête sur `-stay_open False`,
+    /// sauf au premier lancement où il exécute `first` (plantage, blocage) avant de répondre
+    fn fake_exiftool(dir: &Path, first: &str) -> PathBuf {
+        let script = dir.join("fake-exiftool");
+        let body = format!(
+            r#"#!/bin/sh
+state="$0.started"
+while IFS= read -r line; do
+  case "$line" in
+    -execute*)
+      if [ ! -e "$state" ]; then : > "$state"; {first}; fi
+      echo "ok"; echo "{{ready${{line#-execute}}}}";;
+    False) exit 0;;
+  esac
+done
+"#
+        );
+        fs::write(&script, body).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    #[test]
+    fn answers_until_ready_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = ExiftoolPool::with_program(
+            1,
+            fake_exiftool(dir.path(), "true"),
+            Duration::from_secs(10),
+        );
+        assert_eq!(pool.execute(&["-json", "a.jpg"]).unwrap(), "ok\n");
+        assert_eq!(pool.execute(&["-json", "b.jpg"]).unwrap(), "ok\n");
+    }
+
+    #[test]
+    fn restarts_after_crash() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = ExiftoolPool::with_program(
+            1,
+            fake_exiftool(dir.path(), "exit 1"),
+            Duration::from_secs(10),
+        );
+        assert_eq!(pool.execute(&["a.jpg"]).unwrap(), "ok\n");
+    }
+
+    #[test]
+    fn kills_after_timeout_and_restarts_for_the_next_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = ExiftoolPool::with_program(
+            1,
+            fake_exiftool(dir.path(), "exec sleep 30"),
+            Duration::from_millis(300),
+        );
+        let start = Instant::now();
+        // La requête bloquante n'est pas renvoyée: elle bloquerait de nouveau
+        let err = pool.execute(&["a.jpg"]).unwrap_err();
+        assert!(err.to_string().contains("did not answer"), "{:#}", err);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(pool.execute(&["b.jpg"]).unwrap(), "ok\n");
+    }
+
+    #[test]
+    fn gives_up_when_crashing_repeatedly() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-exiftool");
+        fs::write(&script, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        let pool = ExiftoolPool::with_program(1, script, Duration::from_secs(10));
+        let err = pool.execute(&["a.jpg"]).unwrap_err();
+        assert!(format!("{:#}", err).contains("keeps crashing"), "{:#}", err);
+    }
+
+    #[test]
+    fn refuses_line_breaks_in_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = ExiftoolPool::with_program(
+            1,
+            fake_exiftool(dir.path(), "true"),
+            Duration::from_secs(10),
+        );
+        let err = pool.execute(&["-json", "a\n-Comment=x\n.jpg"]).unwrap_err();
+        assert!(err.to_string().contains("line break"), "{:#}", err);
+        // Rien n'a été envoyé: le processus n'a même pas été lancé
+        assert!(!dir.path().join("fake-exiftool.started").exists());
+    }
+}
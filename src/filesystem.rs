@@ -1,12 +1,14 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use blake3::Hasher;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs;
-use std::io::Read;
-use std::path::Path;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
 use walkdir::WalkDir;
 
-use crate::config::FILE_READ_BUFFER_SIZE;
+use crate::config::{TransferMode, FILE_READ_BUFFER_SIZE};
 
 /// Vérifie si un fichier a une extension supportée.
 ///
@@ -61,10 +63,198 @@ pub fn hash_prefix(hash: &blake3::Hash, n: usize) -> String {
     hex[..n.min(hex.len())].to_string().to_uppercase()
 }
 
-/// Déplace ou copie un fichier de src vers dest.
+/// Réservations des noms de destination pendant le traitement parallèle.
 ///
-/// Tente d'abord un rename (rapide), puis fallback sur copy+remove si nécessaire
-/// (utile pour les déplacements cross-device ou sur Windows).
+/// Un worker réserve le nom de base de sa destination avant de tester
+/// son existence; tout autre worker visant le même nom attend que la
+/// réservation soit libérée. Le test d'existence, la résolution des conflits
+/// et le déplacement deviennent ainsi atomiques au sein du processus.
+#[derive(Debug, Default)]
+pub struct DestReservations {
+    taken: Mutex<HashSet<PathBuf>>,
+    released: Condvar,
+}
+
+/// Réservation d'un nom de destination, libérée à la destruction
+pub struct Reservation<'a> {
+    owner: &'a DestReservations,
+    dest: PathBuf,
+}
+
+impl DestReservations {
+    /// Crée un registre de réservations vide
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Réserve un nom de destination, en attendant s'il est déjà réservé.
+    ///
+    /// # Arguments
+    /// * `dest` - Chemin de destination (nom de base, sans suffixe de collision)
+    ///
+    /// # Returns
+    /// La réservation, libérée automatiquement à sa destruction
+    pub fn reserve(&self, dest: &Path) -> Reservation<'_> {
+        let mut taken = self.taken.lock().unwrap_or_else(|e| e.into_inner());
+        while taken.contains(dest) {
+            taken = self.released.wait(taken).unwrap_or_else(|e| e.into_inner());
+        }
+        taken.insert(dest.to_path_buf());
+
+        Reservation {
+            owner: self,
+            dest: dest.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        let mut taken = self.owner.taken.lock().unwrap_or_else(|e| e.into_inner());
+        taken.remove(&self.dest);
+        self.owner.released.notify_all();
+    }
+}
+
+/// Système de fichiers simulé du dry-run.
+///
+/// En dry-run, aucun fichier n'est écrit: les destinations resteraient libres pour
+/// les fichiers suivants et l'aperçu ne montrerait ni conflit ni renommage entre
+/// deux fichiers du dépôt. Les créations et suppressions simulées sont donc
+/// enregistrées ici, et consultées à la place du disque pour ces chemins.
+#[derive(Debug, Default)]
+pub struct DryRunOverlay {
+    files: Mutex<OverlayFiles>,
+}
+
+#[derive(Debug, Default)]
+struct OverlayFiles {
+    /// Fichiers créés, avec le hash de leur contenu
+    created: HashMap<PathBuf, blake3::Hash>,
+    /// Fichiers retirés (sources déplacées ou mises à la corbeille)
+    removed: HashSet<PathBuf>,
+}
+
+impl DryRunOverlay {
+    /// Crée un overlay vide (aucune différence avec le disque)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enregistre la création simulée d'un fichier
+    pub fn create(&self, path: &Path, hash: &blake3::Hash) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.removed.remove(path);
+        files.created.insert(path.to_path_buf(), *hash);
+    }
+
+    /// Enregistre la suppression simulée d'un fichier
+    pub fn remove(&self, path: &Path) {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.created.remove(path);
+        files.removed.insert(path.to_path_buf());
+    }
+
+    /// Indique si un fichier existe, compte tenu des opérations simulées
+    pub fn exists(&self, path: &Path) -> bool {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.created.contains_key(path) || (!files.removed.contains(path) && path.exists())
+    }
+
+    /// Indique si un fichier a été retiré par une opération simulée
+    pub fn is_removed(&self, path: &Path) -> bool {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files.removed.contains(path)
+    }
+
+    /// Hash BLAKE3 d'un fichier, créé par une opération simulée ou lu sur disque
+    ///
+    /// # Errors
+    /// Retourne une erreur si le fichier ne peut pas être lu
+    pub fn hash(&self, path: &Path) -> Result<blake3::Hash> {
+        let created = {
+            let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+            files.created.get(path).copied()
+        };
+        match created {
+            Some(hash) => Ok(hash),
+            None => blake3_file(path),
+        }
+    }
+}
+
+/// Indique si un fichier de la bibliothèque est un lien symbolique vers `src`
+/// (classé par `--mode symlink`): ce n'est pas une copie, mais la source elle-même.
+///
+/// # Arguments
+/// * `link` - Fichier de la bibliothèque
+/// * `src` - Fichier du dépôt
+pub fn links_to(link: &Path, src: &Path) -> bool {
+    let is_link = fs::symlink_metadata(link).is_ok_and(|m| m.file_type().is_symlink());
+    is_link
+        && match (fs::canonicalize(link), fs::canonicalize(src)) {
+            (Ok(target), Ok(src)) => target == src,
+            _ => false,
+        }
+}
+
+/// Transfère un fichier vers la bibliothèque selon le mode choisi, sans jamais écraser dest.
+///
+/// # Arguments
+/// * `src` - Chemin source
+/// * `dest` - Chemin destination
+/// * `mode` - Mode de transfert (move, copy, hardlink, symlink, reflink)
+/// * `dry_run` - Si true, simule l'opération sans la réaliser
+///
+/// # Errors
+/// Retourne une erreur si le transfert échoue, ou si dest existe déjà
+pub fn transfer(src: &Path, dest: &Path, mode: TransferMode, dry_run: bool) -> Result<()> {
+    if mode == TransferMode::Move {
+        return move_or_copy(src, dest, dry_run);
+    }
+
+    if let Some(parent) = dest.parent() {
+        if !dry_run {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create_dir_all {}", parent.display()))?;
+        }
+    }
+
+    let label = match mode {
+        TransferMode::Move => "MOVE",
+        TransferMode::Copy => "COPY",
+        TransferMode::Hardlink => "HARDLINK",
+        TransferMode::Symlink => "SYMLINK",
+        TransferMode::Reflink => "REFLINK",
+    };
+    log::info!("[{}] {} -> {}", label, src.display(), dest.display());
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let res = match mode {
+        TransferMode::Move => unreachable!("handled by move_or_copy"),
+        TransferMode::Copy => copy_noclobber(src, dest),
+        TransferMode::Hardlink => fs::hard_link(src, dest),
+        TransferMode::Symlink => symlink_file(src, dest),
+        TransferMode::Reflink => reflink_or_copy(src, dest),
+    };
+    res.with_context(|| {
+        format!(
+            "{} failed: {} -> {}",
+            label.to_lowercase(),
+            src.display(),
+            dest.display()
+        )
+    })
+}
+
+/// Déplace ou copie un fichier de src vers dest, sans jamais écraser dest.
+///
+/// Tente d'abord un rename sans écrasement (rapide), puis fallback sur une copie
+/// vérifiée + remove si nécessaire (utile pour les déplacements cross-device ou sur Windows).
+/// La source n'est supprimée qu'une fois la copie vérifiée et en place.
 ///
 /// # Arguments
 /// * `src` - Chemin source
@@ -75,7 +265,7 @@ pub fn hash_prefix(hash: &blake3::Hash, n: usize) -> String {
 /// Ok si l'opération réussit
 ///
 /// # Errors
-/// Retourne une erreur si le déplacement/copie échoue
+/// Retourne une erreur si le déplacement/copie échoue, ou si dest existe déjà
 pub fn move_or_copy(src: &Path, dest: &Path, dry_run: bool) -> Result<()> {
     // Crée le dossier cible si nécessaire
     if let Some(parent) = dest.parent() {
@@ -91,13 +281,19 @@ pub fn move_or_copy(src: &Path, dest: &Path, dry_run: bool) -> Result<()> {
         return Ok(());
     }
 
-    // On tente un rename (rapide)…
-    match fs::rename(src, dest) {
+    // On tente un rename sans écrasement (rapide)…
+    match rename_noclobber(src, dest) {
         Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e).with_context(|| {
+            format!(
+                "destination appeared concurrently, not overwriting: {}",
+                dest.display()
+            )
+        }),
         Err(rename_err) => {
-            // …et en cas d'échec, on tente un fallback copy+remove,
+            // …et en cas d'échec, on tente un fallback copie vérifiée + remove,
             // qui marche aussi cross-device et sur Windows.
-            fs::copy(src, dest).with_context(|| {
+            copy_verified(src, dest).with_context(|| {
                 format!(
                     "rename failed ({}) and copy failed: {} -> {}",
                     rename_err,
@@ -119,21 +315,313 @@ pub fn move_or_copy(src: &Path, dest: &Path, dry_run: bool) -> Result<()> {
     }
 }
 
+/// Opération réalisée sur un fichier d'un groupe (pour l'annuler si le groupe échoue)
+pub struct Applied<'a> {
+    pub src: &'a Path,
+    pub dest: PathBuf,
+    /// La source a quitté le dépôt (déplacement ou corbeille)
+    pub removed_source: bool,
+}
+
+/// Remet en place les fichiers d'un groupe déjà transférés (du dernier au premier)
+pub fn rollback_group(done: &[Applied]) {
+    for op in done.iter().rev() {
+        let result = if op.removed_source {
+            move_or_copy(&op.dest, op.src, false)
+        } else {
+            fs::remove_file(&op.dest).with_context(|| format!("remove {}", op.dest.display()))
+        };
+        match result {
+            Ok(()) => log::warn!("[ROLLBACK] {} -> {}", op.dest.display(), op.src.display()),
+            Err(e) => log::error!("[ROLLBACK] {}: {:#}", op.dest.display(), e),
+        }
+    }
+}
+
+/// Copie src vers dest en passant par un fichier temporaire vérifié.
+///
+/// La copie est écrite sous un nom temporaire dans le dossier cible, synchronisée
+/// sur disque, puis son hash BLAKE3 est comparé à celui de la source. Elle n'est
+/// renommée (sans écrasement) vers dest que si les contenus sont identiques.
+///
+/// # Errors
+/// Retourne une erreur si la copie échoue, si elle diffère de la source
+/// (copie tronquée, support défaillant), ou si dest existe déjà
+fn copy_verified(src: &Path, dest: &Path) -> Result<()> {
+    copy_verified_with(src, dest, copy_noclobber)
+}
+
+/// `copy_verified` avec une fonction de copie donnée (un support défaillant en test)
+fn copy_verified_with(
+    src: &Path,
+    dest: &Path,
+    copy: impl Fn(&Path, &Path) -> io::Result<()>,
+) -> Result<()> {
+    let tmp = temp_path_for(dest);
+
+    copy(src, &tmp).with_context(|| format!("copy to {}", tmp.display()))?;
+
+    let verified = blake3_file(src).and_then(|src_hash| {
+        let tmp_hash = blake3_file(&tmp)?;
+        Ok(src_hash == tmp_hash)
+    });
+
+    match verified {
+        Ok(true) => {}
+        Ok(false) => {
+            let _ = fs::remove_file(&tmp);
+            bail!(
+                "copy verification failed (hash mismatch): {}",
+                tmp.display()
+            );
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.context("copy verification failed"));
+        }
+    }
+
+    if let Err(e) = rename_noclobber(&tmp, dest) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e).with_context(|| format!("rename {} -> {}", tmp.display(), dest.display()));
+    }
+    sync_parent_dir(dest);
+    Ok(())
+}
+
+/// Nom temporaire (caché) dans le dossier de dest, propre à ce processus
+fn temp_path_for(dest: &Path) -> PathBuf {
+    let name = dest
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    dest.with_file_name(format!(".{}.{}.mecla-tmp", name, std::process::id()))
+}
+
+/// Synchronise le dossier parent pour rendre le renommage durable (best effort)
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = fs::File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+/// Synchronise le dossier parent pour rendre le renommage durable (best effort)
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+/// Renomme src en dest de façon atomique, en échouant (`AlreadyExists`) si dest existe.
+///
+/// Linux: `renameat2(RENAME_NOREPLACE)`, avec repli sur `link` + `unlink`
+/// si le système de fichiers ne le supporte pas.
+#[cfg(target_os = "linux")]
+fn rename_noclobber(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_src = CString::new(src.as_os_str().as_bytes())?;
+    let c_dest = CString::new(dest.as_os_str().as_bytes())?;
+
+    // Appel système direct: renameat2 n'est pas exposé par toutes les libc (musl)
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            c_src.as_ptr(),
+            libc::AT_FDCWD,
+            c_dest.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if ret == 0 {
+        return Ok(());
+    }
+
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EINVAL) | Some(libc::ENOSYS) => link_then_unlink(src, dest),
+        _ => Err(err),
+    }
+}
+
+/// Renomme src en dest de façon atomique, en échouant (`AlreadyExists`) si dest existe.
+///
+/// macOS: `renamex_np(RENAME_EXCL)`.
+#[cfg(target_os = "macos")]
+fn rename_noclobber(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_src = CString::new(src.as_os_str().as_bytes())?;
+    let c_dest = CString::new(dest.as_os_str().as_bytes())?;
+
+    let ret = unsafe { libc::renamex_np(c_src.as_ptr(), c_dest.as_ptr(), libc::RENAME_EXCL) };
+    if ret == 0 {
+        return Ok(());
+    }
+
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOTSUP) => link_then_unlink(src, dest),
+        _ => Err(err),
+    }
+}
+
+/// Renomme src en dest, en échouant (`AlreadyExists`) si dest existe.
+///
+/// Autres systèmes: `link` + `unlink` (le lien échoue si dest existe).
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn rename_noclobber(src: &Path, dest: &Path) -> io::Result<()> {
+    link_then_unlink(src, dest)
+}
+
+/// Crée un lien dur dest vers src puis supprime src (échoue si dest existe)
+fn link_then_unlink(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::hard_link(src, dest)?;
+    fs::remove_file(src)
+}
+
+/// Copie src vers dest en créant dest de façon exclusive (`create_new`)
+fn copy_noclobber(src: &Path, dest: &Path) -> io::Result<()> {
+    let mut input = fs::File::open(src)?;
+    let output = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+    copy_into(&mut input, output, dest)
+}
+
+/// Copie le contenu de `input` dans `output` (fichier `dest` fraîchement créé, vide)
+fn copy_into(input: &mut fs::File, mut output: fs::File, dest: &Path) -> io::Result<()> {
+    if let Err(e) = io::copy(input, &mut output).and_then(|_| output.sync_all()) {
+        // Ne pas laisser de copie partielle derrière nous
+        drop(output);
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+
+    // Conserve les permissions et la date de modification, comme fs::copy
+    let meta = input.metadata()?;
+    fs::set_permissions(dest, meta.permissions())?;
+    if let Ok(mtime) = meta.modified() {
+        let _ = output.set_modified(mtime);
+    }
+    Ok(())
+}
+
+/// Clone src vers dest (`FICLONE`, btrfs/XFS), ou copie classique si le clonage
+/// n'est pas supporté (autre système de fichiers, cross-device).
+#[cfg(target_os = "linux")]
+fn reflink_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let mut input = fs::File::open(src)?;
+    let output = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest)?;
+
+    let ret = unsafe { libc::ioctl(output.as_raw_fd(), libc::FICLONE, input.as_raw_fd()) };
+    if ret == 0 {
+        return Ok(());
+    }
+
+    log::debug!(
+        "reflink not supported ({}), copying instead: {}",
+        io::Error::last_os_error(),
+        dest.display()
+    );
+    copy_into(&mut input, output, dest)
+}
+
+/// Reflink non disponible sur ce système: copie classique
+#[cfg(not(target_os = "linux"))]
+fn reflink_or_copy(src: &Path, dest: &Path) -> io::Result<()> {
+    copy_noclobber(src, dest)
+}
+
+/// Crée un lien symbolique dest -> src
+#[cfg(unix)]
+fn symlink_file(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(src, dest)
+}
+
+/// Crée un lien symbolique dest -> src
+#[cfg(windows)]
+fn symlink_file(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(src, dest)
+}
+
+/// Liste les fichiers ouverts en écriture par un processus (`/proc/<pid>/fd`).
+///
+/// Seuls les processus accessibles à l'utilisateur courant sont examinés.
+#[cfg(target_os = "linux")]
+pub fn files_open_for_writing() -> HashSet<PathBuf> {
+    let mut out = HashSet::new();
+    let Ok(procs) = fs::read_dir("/proc") else {
+        return out;
+    };
+
+    for proc_dir in procs.filter_map(Result::ok) {
+        let is_pid = proc_dir
+            .file_name()
+            .to_str()
+            .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()));
+        if !is_pid {
+            continue;
+        }
+        let Ok(fds) = fs::read_dir(proc_dir.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.filter_map(Result::ok) {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            // Mode d'ouverture: O_WRONLY (1) ou O_RDWR (2)
+            let info = proc_dir.path().join("fdinfo").join(fd.file_name());
+            let writable = fs::read_to_string(info)
+                .ok()
+                .and_then(|s| {
+                    s.lines()
+                        .find_map(|l| l.strip_prefix("flags:"))
+                        .and_then(|f| u32::from_str_radix(f.trim(), 8).ok())
+                })
+                .is_some_and(|flags| flags & 0o3 != 0);
+            if writable {
+                out.insert(target);
+            }
+        }
+    }
+    out
+}
+
+/// Liste les fichiers ouverts en écriture (non disponible: seule la stabilité compte)
+#[cfg(not(target_os = "linux"))]
+pub fn files_open_for_writing() -> HashSet<PathBuf> {
+    HashSet::new()
+}
+
 /// Vérifie si un répertoire contient des fichiers média supportés.
 ///
 /// # Arguments
 /// * `root` - Racine du répertoire à vérifier
 /// * `exts` - Liste des extensions supportées
+/// * `overlay` - Fichiers retirés par le dry-run (ignorés)
 ///
 /// # Returns
 /// true si au moins un fichier supporté est trouvé, false sinon
-pub fn contains_supported_media(root: &Path, exts: &[String]) -> bool {
+pub fn contains_supported_media(root: &Path, exts: &[String], overlay: &DryRunOverlay) -> bool {
     for entry in WalkDir::new(root)
         .follow_links(false)
         .into_iter()
         .filter_map(Result::ok)
     {
-        if entry.file_type().is_file() && is_supported(entry.path(), exts) {
+        if entry.file_type().is_file()
+            && is_supported(entry.path(), exts)
+            && !overlay.is_removed(entry.path())
+        {
             return true;
         }
     }
@@ -180,10 +668,133 @@ pub fn prune_empty_dirs_recursively(root: &Path) -> Result<()> {
             if is_dir_empty(p)? {
                 // Ne supprime pas 'root' ici, on le gère après
                 if p != root {
-                    fs::remove_dir(p).with_context(|| format!("remove empty dir {}", p.display()))?;
+                    fs::remove_dir(p)
+                        .with_context(|| format!("remove empty dir {}", p.display()))?;
                 }
             }
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Dossier temporaire avec une source `src.jpg`
+    fn setup() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.jpg");
+        fs::write(&src, b"photo").unwrap();
+        (dir, src)
+    }
+
+    /// Fichiers restants dans un dossier (fichiers temporaires compris)
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn transfer_modes_create_the_destination() {
+        for mode in [
+            TransferMode::Move,
+            TransferMode::Copy,
+            TransferMode::Hardlink,
+            TransferMode::Symlink,
+            TransferMode::Reflink,
+        ] {
+            let (dir, src) = setup();
+            let dest = dir.path().join("2024/05/dest.jpg");
+            transfer(&src, &dest, mode, false).unwrap();
+
+            assert_eq!(fs::read(&dest).unwrap(), b"photo", "{:?}", mode);
+            assert_eq!(src.exists(), mode != TransferMode::Move, "{:?}", mode);
+            let is_link = fs::symlink_metadata(&dest)
+                .unwrap()
+                .file_type()
+                .is_symlink();
+            assert_eq!(is_link, mode == TransferMode::Symlink, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn transfer_modes_never_overwrite() {
+        for mode in [
+            TransferMode::Move,
+            TransferMode::Copy,
+            TransferMode::Hardlink,
+            TransferMode::Symlink,
+            TransferMode::Reflink,
+        ] {
+            let (dir, src) = setup();
+            let dest = dir.path().join("dest.jpg");
+            fs::write(&dest, b"other").unwrap();
+
+            assert!(transfer(&src, &dest, mode, false).is_err(), "{:?}", mode);
+            assert_eq!(fs::read(&dest).unwrap(), b"other", "{:?}", mode);
+            assert_eq!(fs::read(&src).unwrap(), b"photo", "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn dry_run_transfers_nothing() {
+        let (dir, src) = setup();
+        let dest = dir.path().join("2024/dest.jpg");
+        transfer(&src, &dest, TransferMode::Move, true).unwrap();
+        transfer(&src, &dest, TransferMode::Copy, true).unwrap();
+        assert!(src.exists());
+        assert!(!dir.path().join("2024").exists());
+    }
+
+    #[test]
+    fn noclobber_primitives_refuse_existing_destination() {
+        let (dir, src) = setup();
+        let dest = dir.path().join("dest.jpg");
+        fs::write(&dest, b"other").unwrap();
+
+        let err = rename_noclobber(&src, &dest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        let err = copy_noclobber(&src, &dest).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&dest).unwrap(), b"other");
+        assert!(src.exists());
+    }
+
+    #[test]
+    fn verified_copy_renames_into_place() {
+        let (dir, src) = setup();
+        let dest = dir.path().join("dest.jpg");
+        copy_verified(&src, &dest).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+        assert_eq!(names(dir.path()), ["dest.jpg", "src.jpg"]);
+    }
+
+    #[test]
+    fn verified_copy_never_overwrites() {
+        let (dir, src) = setup();
+        let dest = dir.path().join("dest.jpg");
+        fs::write(&dest, b"other").unwrap();
+
+        assert!(copy_verified(&src, &dest).is_err());
+        assert_eq!(fs::read(&dest).unwrap(), b"other");
+        assert_eq!(names(dir.path()), ["dest.jpg", "src.jpg"]);
+    }
+
+    #[test]
+    fn verified_copy_rejects_a_corrupted_copy() {
+        let (dir, src) = setup();
+        let dest = dir.path().join("dest.jpg");
+        let truncating = |_: &Path, tmp: &Path| fs::write(tmp, b"pho");
+
+        let err = copy_verified_with(&src, &dest, truncating).unwrap_err();
+        assert!(err.to_string().contains("hash mismatch"), "{:#}", err);
+        // Source intacte, ni destination ni fichier temporaire
+        assert_eq!(fs::read(&src).unwrap(), b"photo");
+        assert_eq!(names(dir.path()), ["src.jpg"]);
+    }
+}
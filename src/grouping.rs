@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::SIDECAR_EXTENSIONS;
+use crate::media::MediaKind;
+use crate::metadata::MediaMetadata;
+
+/// Fichiers d'un même dossier partageant un nom de base (`IMG_0001.jpg`,
+/// `IMG_0001.cr2`, `IMG_0001.xmp`…), classés ensemble sous un même nom.
+#[derive(Debug, Clone)]
+pub struct MediaGroup {
+    /// Fichier principal: sa date et son hash déterminent le nom du groupe
+    pub leader: PathBuf,
+    /// Autres membres (sidecars, RAW, médias de même nom)
+    pub companions: Vec<PathBuf>,
+    /// Nom de base commun (tel qu'écrit dans le nom du fichier principal)
+    pub stem: String,
+}
+
+impl MediaGroup {
+    /// Tous les membres du groupe, le fichier principal en premier
+    pub fn members(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.leader).chain(&self.companions)
+    }
+
+    /// Nombre de fichiers du groupe
+    pub fn len(&self) -> usize {
+        1 + self.companions.len()
+    }
+
+    /// Partie du nom d'un membre qui suit le nom de base, en minuscules et sans le
+    /// point initial (ex: `jpg`, `cr2.xmp`). Pour un membre d'un autre nom (vidéo
+    /// d'une Live Photo), seule sa dernière extension est gardée.
+    pub fn member_ext(&self, path: &Path) -> Option<String> {
+        let name = path.file_name()?.to_str()?;
+        let rest = name
+            .get(..self.stem.len())
+            .filter(|head| head.eq_ignore_ascii_case(&self.stem))
+            .and_then(|_| name[self.stem.len()..].strip_prefix('.'));
+        match rest {
+            Some(rest) if !rest.is_empty() => Some(rest.to_lowercase()),
+            _ => extension_of(path),
+        }
+    }
+}
+
+/// Indique si un fichier peut accompagner un média (sidecar ou RAW), même si son
+/// extension n'est pas dans `--ext`
+pub fn is_companion(path: &Path) -> bool {
+    extension_of(path).is_some_and(|e| {
+        SIDECAR_EXTENSIONS.contains(&e.as_str()) || MediaKind::from_ext(&e) == Some(MediaKind::Raw)
+    })
+}
+
+/// Regroupe les fichiers d'un même dossier qui partagent un nom de base.
+///
+/// Le nom de base est le nom sans extension, sans tenir compte de la casse; pour un
+/// sidecar à double extension (`IMG_0001.CR2.xmp`), l'extension du média est aussi
+/// retirée. Des fichiers dont le nom complet ne diffère que par la casse sont placés
+/// dans des groupes distincts. Chaque groupe doit contenir au moins un fichier
+/// supporté: les sidecars orphelins sont ignorés. Le fichier principal est le membre supporté dont
+/// l'extension vient en premier dans `exts`.
+///
+/// # Arguments
+/// * `files` - Fichiers supportés et compagnons trouvés dans le dépôt
+/// * `exts` - Extensions supportées, par ordre de préférence
+///
+/// # Returns
+/// Les groupes, dans l'ordre des chemins
+pub fn group_by_stem(files: Vec<PathBuf>, exts: &[String]) -> Vec<MediaGroup> {
+    let mut by_key: BTreeMap<(PathBuf, String), Vec<PathBuf>> = BTreeMap::new();
+    for f in files {
+        let stem = match base_stem(&f, exts) {
+            Some(s) => s.to_lowercase(),
+            None => continue,
+        };
+        let dir = f.parent().unwrap_or(Path::new("")).to_path_buf();
+        by_key.entry((dir, stem)).or_default().push(f);
+    }
+
+    let rank = |p: &PathBuf| {
+        extension_of(p)
+            .and_then(|e| exts.iter().position(|x| *x == e))
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut groups = Vec::with_capacity(by_key.len());
+    for mut members in by_key.into_values().flat_map(split_case_variants) {
+        let leader_idx = match members
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| rank(p) != usize::MAX)
+            .min_by_key(|(_, p)| rank(p))
+        {
+            Some((i, _)) => i,
+            None => {
+                for orphan in &members {
+                    log::debug!("[SKIP] companion without media: {}", orphan.display());
+                }
+                continue;
+            }
+        };
+
+        let leader = members.remove(leader_idx);
+        let stem = match base_stem(&leader, exts) {
+            Some(s) => s.to_string(),
+            None => continue,
+        };
+        groups.push(MediaGroup {
+            leader,
+            companions: members,
+            stem,
+        });
+    }
+    groups
+}
+
+/// Sépare les fichiers d'un même nom de base dont les noms ne diffèrent que par la
+/// casse (`IMG_1.jpg`, `IMG_1.JPG`): ils auraient la même destination. Chaque lot
+/// reçoit au plus un fichier de chaque nom; les lots suivants forment d'autres groupes.
+fn split_case_variants(mut files: Vec<PathBuf>) -> Vec<Vec<PathBuf>> {
+    files.sort();
+    let mut lots: Vec<(HashSet<String>, Vec<PathBuf>)> = Vec::new();
+    for f in files {
+        let name = f
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match lots.iter_mut().find(|(names, _)| !names.contains(&name)) {
+            Some((names, lot)) => {
+                names.insert(name);
+                lot.push(f);
+            }
+            None => lots.push((HashSet::from([name]), vec![f])),
+        }
+    }
+    lots.into_iter().map(|(_, lot)| lot).collect()
+}
+
+/// Rattache la vidéo d'une Live Photo Apple au groupe de sa photo.
+///
+/// Les deux moitiés partagent un `ContentIdentifier`, mais leurs dates peuvent
+/// différer d'une seconde et elles peuvent être dans des dossiers différents:
+/// la vidéo (et ses compagnons) rejoint le groupe de la photo, dont elle prend
+/// la date, le nom de base et le dossier de destination. Une seule vidéo rejoint
+/// chaque photo: une autre vidéo de même identifiant (copie, réexport) aurait la
+/// même destination et reste un groupe à part.
+///
+/// Seuls les groupes d'un même appel sont appariés: en `mecla watch`, une moitié
+/// arrivée dans un lot ultérieur retrouve l'autre via `LivePhotoPartners`.
+///
+/// # Arguments
+/// * `groups` - Groupes formés par `group_by_stem`
+/// * `metas` - Métadonnées des fichiers principaux
+///
+/// # Returns
+/// Les groupes, les vidéos appariées étant fusionnées dans le groupe de leur photo
+pub fn pair_live_photos(
+    groups: Vec<MediaGroup>,
+    metas: &HashMap<PathBuf, MediaMetadata>,
+) -> Vec<MediaGroup> {
+    let content_id = |g: &MediaGroup| {
+        metas
+            .get(&g.leader)
+            .and_then(|m| m.content_identifier.clone())
+    };
+    let is_video = |g: &MediaGroup| MediaKind::from_path(&g.leader) == MediaKind::Video;
+
+    // Première photo de chaque identifiant
+    let mut photo_of: HashMap<String, usize> = HashMap::new();
+    for (i, g) in groups.iter().enumerate() {
+        if !is_video(g) {
+            if let Some(id) = content_id(g) {
+                photo_of.entry(id).or_insert(i);
+            }
+        }
+    }
+
+    let mut merged: Vec<Option<MediaGroup>> = groups.into_iter().map(Some).collect();
+    for i in 0..merged.len() {
+        let target = match merged[i].as_ref() {
+            Some(g) if is_video(g) => content_id(g).and_then(|id| photo_of.get(&id).copied()),
+            _ => None,
+        };
+        let Some(target) = target else {
+            continue;
+        };
+
+        let video = merged[i].take().expect("group present");
+        match merged[target].as_mut() {
+            Some(photo) if !shares_a_destination(photo, &video) => {
+                log::debug!(
+                    "[LIVE] {} paired with {}",
+                    video.leader.display(),
+                    photo.leader.display()
+                );
+                photo.companions.extend(video.members().cloned());
+            }
+            _ => merged[i] = Some(video),
+        }
+    }
+    merged.into_iter().flatten().collect()
+}
+
+/// Moitiés de Live Photos déjà classées pendant l'exécution, par `ContentIdentifier`.
+///
+/// En `mecla watch`, une photo et sa vidéo peuvent devenir stables dans des lots
+/// différents, donc hors de portée de `pair_live_photos`: la seconde moitié est
+/// alors classée à côté de la première, sous le même nom de base.
+#[derive(Default)]
+pub struct LivePhotoPartners {
+    /// Destination du fichier principal de la première moitié classée
+    sorted: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl LivePhotoPartners {
+    /// Crée une table vide
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enregistre la destination d'une moitié classée (la première seulement)
+    pub fn record(&self, id: &str, dest: &Path) {
+        let mut sorted = self.sorted.lock().unwrap_or_else(|e| e.into_inner());
+        sorted
+            .entry(id.to_string())
+            .or_insert_with(|| dest.to_path_buf());
+    }
+
+    /// Destination de l'autre moitié d'une Live Photo, si elle est déjà classée
+    ///
+    /// # Arguments
+    /// * `id` - `ContentIdentifier` du fichier principal
+    /// * `leader` - Fichier principal (photo ou vidéo)
+    pub fn partner(&self, id: &str, leader: &Path) -> Option<PathBuf> {
+        let is_video = |p: &Path| MediaKind::from_path(p) == MediaKind::Video;
+        let sorted = self.sorted.lock().unwrap_or_else(|e| e.into_inner());
+        sorted
+            .get(id)
+            .filter(|dest| is_video(dest) != is_video(leader))
+            .cloned()
+    }
+}
+
+/// Indique si un membre de `other` aurait la même destination qu'un membre de `group`
+/// une fois rattaché à ce groupe (même extension)
+fn shares_a_destination(group: &MediaGroup, other: &MediaGroup) -> bool {
+    let exts: HashSet<String> = group
+        .members()
+        .filter_map(|m| group.member_ext(m))
+        .collect();
+    other
+        .members()
+        .filter_map(|m| group.member_ext(m))
+        .any(|e| exts.contains(&e))
+}
+
+/// Nom de base d'un fichier (voir `group_by_stem`)
+fn base_stem<'a>(path: &'a Path, exts: &[String]) -> Option<&'a str> {
+    let stem = path.file_stem()?.to_str()?;
+    if !is_companion(path) || exts.contains(&extension_of(path)?) {
+        return Some(stem);
+    }
+
+    // Sidecar nommé d'après le fichier complet: IMG_0001.CR2.xmp -> IMG_0001
+    let inner = Path::new(stem);
+    match inner.extension().and_then(OsStr::to_str) {
+        Some(e)
+            if exts.contains(&e.to_lowercase())
+                || MediaKind::from_ext(&e.to_lowercase()).is_some()
+                || SIDECAR_EXTENSIONS.contains(&e.to_lowercase().as_str()) =>
+        {
+            inner.file_stem().and_then(OsStr::to_str)
+        }
+        _ => Some(stem),
+    }
+}
+
+/// Extension d'un fichier, en minuscules
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map(|e| e.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::media::default_extensions;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(|n| Path::new("depot").join(n)).collect()
+    }
+
+    fn names(g: &MediaGroup) -> Vec<String> {
+        g.members()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn companions_follow_their_media() {
+        assert!(is_companion(Path::new("IMG_0001.xmp")));
+        assert!(is_companion(Path::new("IMG_0001.AAE")));
+        assert!(is_companion(Path::new("IMG_0001.CR2")));
+        assert!(is_companion(Path::new("IMG_0001.cr2.xmp")));
+        assert!(!is_companion(Path::new("IMG_0001.jpg")));
+        assert!(!is_companion(Path::new("IMG_0001.mov")));
+        assert!(!is_companion(Path::new("IMG_0001")));
+    }
+
+    #[test]
+    fn groups_raw_jpeg_and_sidecars_by_stem() {
+        let files = paths(&[
+            "IMG_0001.CR2",
+            "IMG_0001.jpg",
+            "IMG_0001.CR2.xmp",
+            "img_0001.aae",
+            "IMG_0002.jpg",
+            "IMG_0003.xmp",
+        ]);
+        let groups = group_by_stem(files, &default_extensions());
+
+        // Le JPEG mène le groupe, le sidecar orphelin IMG_0003 est ignoré
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            names(&groups[0]),
+            [
+                "IMG_0001.jpg",
+                "IMG_0001.CR2",
+                "IMG_0001.CR2.xmp",
+                "img_0001.aae"
+            ]
+        );
+        assert_eq!(groups[0].stem, "IMG_0001");
+        assert_eq!(names(&groups[1]), ["IMG_0002.jpg"]);
+
+        let g = &groups[0];
+        let exts: Vec<_> = g.members().map(|p| g.member_ext(p).unwrap()).collect();
+        assert_eq!(exts, ["jpg", "cr2", "cr2.xmp", "aae"]);
+    }
+
+    #[test]
+    fn same_stem_in_other_directories_is_another_group() {
+        let files = vec![
+            PathBuf::from("depot/a/IMG_0001.jpg"),
+            PathBuf::from("depot/b/IMG_0001.xmp"),
+        ];
+        let groups = group_by_stem(files, &default_extensions());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].companions, Vec::<PathBuf>::new());
+    }
+
+    /// Groupes formés à partir des fichiers, avec l'identifiant Live Photo de chacun
+    fn live(files: &[(&str, Option<&str>)]) -> Vec<MediaGroup> {
+        let groups = group_by_stem(
+            files.iter().map(|(f, _)| PathBuf::from(f)).collect(),
+            &default_extensions(),
+        );
+        let metas = files
+            .iter()
+            .map(|(f, id)| {
+                let meta = MediaMetadata {
+                    content_identifier: id.map(str::to_string),
+                    ..Default::default()
+                };
+                (PathBuf::from(f), meta)
+            })
+            .collect();
+        pair_live_photos(groups, &metas)
+    }
+
+    #[test]
+    fn pairs_live_photo_video_from_another_folder() {
+        let groups = live(&[
+            ("depot/Photos/IMG_0001.HEIC", Some("A1")),
+            ("depot/Videos/IMG_0001.MOV", Some("A1")),
+            ("depot/Videos/IMG_0001.AAE", None),
+            ("depot/Videos/IMG_0002.MOV", Some("B2")),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            names(&groups[0]),
+            ["IMG_0001.HEIC", "IMG_0001.MOV", "IMG_0001.AAE"]
+        );
+        let g = &groups[0];
+        let exts: Vec<_> = g.members().map(|p| g.member_ext(p).unwrap()).collect();
+        assert_eq!(exts, ["heic", "mov", "aae"]);
+        // Vidéo sans photo: groupe à part
+        assert_eq!(names(&groups[1]), ["IMG_0002.MOV"]);
+    }
+
+    #[test]
+    fn live_photo_without_identifier_is_not_paired() {
+        let groups = live(&[
+            ("depot/IMG_0001.HEIC", None),
+            ("depot/Videos/IMG_0001.MOV", Some("A1")),
+        ]);
+        assert_eq!(groups.len(), 2);
+
+        let groups = live(&[
+            ("depot/IMG_0001.HEIC", Some("A1")),
+            ("depot/Videos/IMG_0001.MOV", None),
+        ]);
+        assert_eq!(groups.len(), 2);
+    }
+
+    #[test]
+    fn one_video_per_live_photo() {
+        let groups = live(&[
+            ("depot/IMG_0001.HEIC", Some("A1")),
+            ("depot/a/IMG_0001.MOV", Some("A1")),
+            ("depot/b/IMG_0001.MOV", Some("A1")),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(names(&groups[0]), ["IMG_0001.HEIC", "IMG_0001.MOV"]);
+        assert_eq!(
+            groups[0].companions,
+            [PathBuf::from("depot/a/IMG_0001.MOV")]
+        );
+        assert_eq!(groups[1].leader, PathBuf::from("depot/b/IMG_0001.MOV"));
+    }
+
+    #[test]
+    fn names_differing_only_by_case_are_split() {
+        let files = paths(&["IMG_1.JPG", "img_1.jpg", "IMG_1.xmp"]);
+        let groups = group_by_stem(files, &default_extensions());
+        assert_eq!(groups.len(), 2);
+        assert_eq!(names(&groups[0]), ["IMG_1.JPG", "IMG_1.xmp"]);
+        assert_eq!(names(&groups[1]), ["img_1.jpg"]);
+    }
+}
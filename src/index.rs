@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use crate::config::{INDEX_FILE, MECLA_DIR};
+use crate::filesystem::blake3_file;
+use crate::perceptual::{dhash, supports_dhash, DhashTree};
+
+/// Entrée de l'index (une ligne JSON par fichier de la bibliothèque)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    /// Chemin relatif à la racine de la bibliothèque
+    path: PathBuf,
+    size: u64,
+    /// Date de modification, en nanosecondes depuis l'époque UNIX
+    mtime: i64,
+    /// Hash BLAKE3 (hexadécimal) du contenu
+    hash: String,
+    /// Hash perceptuel des images (calculé seulement avec `--near-dup`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dhash: Option<u64>,
+}
+
+/// Fichiers connus par contenu
+#[derive(Default)]
+struct Contents {
+    /// Fichier de la bibliothèque pour chaque hash (présent au démarrage)
+    known: HashMap<blake3::Hash, PathBuf>,
+    /// Destinations réservées pendant l'exécution (fichiers pas encore écrits)
+    claimed: HashMap<blake3::Hash, PathBuf>,
+    /// Hash perceptuel des contenus réservés
+    claimed_dhash: HashMap<blake3::Hash, u64>,
+}
+
+/// Index persistant du contenu de la bibliothèque (`output/.mecla/index.jsonl`).
+///
+/// Associe le hash BLAKE3 de chaque fichier à son chemin, pour reconnaître un
+/// fichier déjà classé quel que soit son nom ou son dossier. Au démarrage, seuls
+/// les fichiers nouveaux ou modifiés (taille ou date de modification) depuis la
+/// dernière exécution sont hashés.
+pub struct LibraryIndex {
+    root: PathBuf,
+    path: PathBuf,
+    /// Fichiers présents au démarrage
+    entries: Vec<IndexEntry>,
+    /// L'index sur disque ne correspond plus à la bibliothèque (fichiers hashés ou disparus)
+    refreshed: bool,
+    contents: Mutex<Contents>,
+    /// Hash perceptuel des images de la bibliothèque et de celles ajoutées, lu par
+    /// tous les workers en parallèle
+    similar: RwLock<DhashTree>,
+}
+
+impl LibraryIndex {
+    /// Charge l'index de la bibliothèque et le met à jour avec son contenu actuel.
+    ///
+    /// # Arguments
+    /// * `output` - Racine de la bibliothèque (l'index va dans `output/.mecla`)
+    /// * `perceptual` - Calcule aussi le hash perceptuel des images (`--near-dup`)
+    ///
+    /// # Errors
+    /// Retourne une erreur si la bibliothèque ne peut pas être parcourue
+    pub fn open(output: &Path, perceptual: bool) -> Result<Self> {
+        let path = output.join(MECLA_DIR).join(INDEX_FILE);
+        let cached = if path.is_file() {
+            read_entries(&path)?
+        } else {
+            HashMap::new()
+        };
+
+        let files = library_files(output)?;
+        let (fresh, stale): (Vec<_>, Vec<_>) = files.into_iter().partition(|(rel, size, mtime)| {
+            cached
+                .get(rel)
+                .is_some_and(|e| e.size == *size && e.mtime == *mtime)
+        });
+        if !stale.is_empty() {
+            log::info!("Indexing library: {} new or modified files", stale.len());
+        }
+
+        let cached_count = cached.len();
+        let hashed: Vec<IndexEntry> = stale
+            .into_par_iter()
+            .filter_map(|(rel, size, mtime)| match blake3_file(&output.join(&rel)) {
+                Ok(hash) => Some(IndexEntry {
+                    path: rel,
+                    size,
+                    mtime,
+                    hash: hash.to_hex().to_string(),
+                    dhash: None,
+                }),
+                Err(e) => {
+                    log::warn!("[INDEX] {}: {:#}", rel.display(), e);
+                    None
+                }
+            })
+            .collect();
+
+        let mut entries: Vec<IndexEntry> = fresh
+            .into_iter()
+            .filter_map(|(rel, _, _)| cached.get(&rel).cloned())
+            .collect();
+        let mut refreshed = !hashed.is_empty() || entries.len() != cached_count;
+        entries.extend(hashed);
+
+        if perceptual {
+            refreshed |= add_perceptual_hashes(output, &mut entries) > 0;
+        }
+
+        let mut contents = Contents::default();
+        let mut similar = DhashTree::default();
+        for e in &entries {
+            if let Ok(hash) = blake3::Hash::from_hex(&e.hash) {
+                contents.known.entry(hash).or_insert_with(|| e.path.clone());
+            }
+            if let Some(d) = e.dhash {
+                similar.insert(d, output.join(&e.path));
+            }
+        }
+
+        Ok(Self {
+            root: output.to_path_buf(),
+            path,
+            entries,
+            refreshed,
+            contents: Mutex::new(contents),
+            similar: RwLock::new(similar),
+        })
+    }
+
+    /// Indique si l'index sur disque a été mis à jour à l'ouverture (voir `save`)
+    pub fn is_refreshed(&self) -> bool {
+        self.refreshed
+    }
+
+    /// Indique si un contenu est déjà dans la bibliothèque (ou en cours d'ajout)
+    pub fn contains(&self, hash: &blake3::Hash) -> bool {
+        let contents = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        contents.claimed.contains_key(hash)
+            || contents
+                .known
+                .get(hash)
+                .is_some_and(|rel| self.root.join(rel).exists())
+    }
+
+    /// Réserve un contenu pour une destination, s'il n'est pas déjà dans la bibliothèque.
+    ///
+    /// # Returns
+    /// None si la destination est retenue, sinon le fichier qui a déjà ce contenu
+    /// (éventuellement en cours de transfert par un autre worker)
+    pub fn claim(&self, hash: &blake3::Hash, dest: &Path) -> Option<PathBuf> {
+        let mut contents = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = contents.claimed.get(hash) {
+            return Some(existing.clone());
+        }
+        if let Some(rel) = contents.known.get(hash) {
+            // Un fichier supprimé depuis l'indexation ne compte plus
+            let existing = self.root.join(rel);
+            if existing.exists() {
+                return Some(existing);
+            }
+        }
+        contents.claimed.insert(*hash, dest.to_path_buf());
+        None
+    }
+
+    /// Libère un contenu réservé dont le transfert n'a pas eu lieu
+    pub fn release(&self, hash: &blake3::Hash) {
+        let mut contents = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        let dest = contents.claimed.remove(hash);
+        if let Some((dest, d)) = dest.zip(contents.claimed_dhash.remove(hash)) {
+            let mut similar = self.similar.write().unwrap_or_else(|e| e.into_inner());
+            similar.remove(d, &dest);
+        }
+    }
+
+    /// Cherche l'image de la bibliothèque la plus proche d'un hash perceptuel.
+    ///
+    /// # Returns
+    /// Le fichier et sa distance, si elle ne dépasse pas `max_distance`
+    pub fn find_similar(&self, dhash: u64, max_distance: u32) -> Option<(PathBuf, u32)> {
+        let similar = self.similar.read().unwrap_or_else(|e| e.into_inner());
+        similar
+            .nearest(dhash, max_distance)
+            .map(|(path, dist)| (path.to_path_buf(), dist))
+    }
+
+    /// Associe un hash perceptuel à un contenu réservé (voir `claim`)
+    pub fn set_dhash(&self, hash: &blake3::Hash, dhash: u64) {
+        let mut contents = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(dest) = contents.claimed.get(hash).cloned() {
+            contents.claimed_dhash.insert(*hash, dhash);
+            let mut similar = self.similar.write().unwrap_or_else(|e| e.into_inner());
+            similar.insert(dhash, dest);
+        }
+    }
+
+    /// Enregistre l'index sur disque (fichiers présents au démarrage et ajoutés depuis).
+    ///
+    /// # Errors
+    /// Retourne une erreur si le fichier d'index ne peut pas être écrit
+    pub fn save(&self) -> Result<()> {
+        let contents = self.contents.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.entries.clone();
+        for (hash, path) in &contents.claimed {
+            // Transfert non abouti ou annulé: rien à indexer
+            let Some((size, mtime)) = file_stamp(path) else {
+                continue;
+            };
+            let rel = path.strip_prefix(&self.root).unwrap_or(path);
+            entries.push(IndexEntry {
+                path: rel.to_path_buf(),
+                size,
+                mtime,
+                hash: hash.to_hex().to_string(),
+                dhash: contents.claimed_dhash.get(hash).copied(),
+            });
+        }
+        write_entries(&self.path, entries)
+    }
+}
+
+/// Retire de l'index des fichiers qui ont quitté la bibliothèque (`mecla undo`).
+///
+/// # Arguments
+/// * `output` - Racine de la bibliothèque
+/// * `removed` - Chemins des fichiers retirés
+///
+/// # Errors
+/// Retourne une erreur si l'index existe mais ne peut pas être lu ou réécrit
+pub fn forget(output: &Path, removed: &[PathBuf]) -> Result<()> {
+    let path = output.join(MECLA_DIR).join(INDEX_FILE);
+    if !path.is_file() {
+        return Ok(());
+    }
+
+    let mut entries = read_entries(&path)?;
+    let before = entries.len();
+    for p in removed {
+        entries.remove(p.strip_prefix(output).unwrap_or(p));
+    }
+    if entries.len() == before {
+        return Ok(());
+    }
+    write_entries(&path, entries.into_values().collect())
+}
+
+/// Écrit le fichier d'index, trié par chemin
+fn write_entries(path: &Path, mut entries: Vec<IndexEntry>) -> Result<()> {
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    // Écriture dans un fichier temporaire puis renommage: l'index n'est jamais tronqué
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create_dir_all {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut w =
+        BufWriter::new(File::create(&tmp).with_context(|| format!("create {}", tmp.display()))?);
+    for entry in &entries {
+        serde_json::to_writer(&mut w, entry).context("serialize index entry")?;
+        w.write_all(b"\n")?;
+    }
+    w.into_inner()
+        .map_err(|e| e.into_error())
+        .and_then(|f| f.sync_all())
+        .with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("rename {} -> {}", tmp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Calcule le hash perceptuel des images qui n'en ont pas encore
+///
+/// # Returns
+/// Le nombre de hashes calculés
+fn add_perceptual_hashes(root: &Path, entries: &mut [IndexEntry]) -> usize {
+    let missing = entries
+        .iter()
+        .filter(|e| e.dhash.is_none() && supports_dhash(&e.path))
+        .count();
+    if missing > 0 {
+        log::info!("Computing perceptual hashes: {} images", missing);
+    }
+
+    entries
+        .par_iter_mut()
+        .filter(|e| e.dhash.is_none() && supports_dhash(&e.path))
+        .filter_map(|e| match dhash(&root.join(&e.path)) {
+            Ok(d) => {
+                e.dhash = Some(d);
+                Some(())
+            }
+            Err(err) => {
+                log::debug!(
+                    "[INDEX] no perceptual hash for {}: {:#}",
+                    e.path.display(),
+                    err
+                );
+                None
+            }
+        })
+        .count()
+}
+
+/// Lit le fichier d'index; les lignes invalides sont ignorées (fichiers re-hashés)
+fn read_entries(path: &Path) -> Result<HashMap<PathBuf, IndexEntry>> {
+    let f = File::open(path).with_context(|| format!("open index {}", path.display()))?;
+    let mut entries = HashMap::new();
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line.with_context(|| format!("read index {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<IndexEntry>(&line) {
+            Ok(entry) => {
+                entries.insert(entry.path.clone(), entry);
+            }
+            Err(e) => log::warn!("[INDEX] ignoring invalid entry at line {}: {}", i + 1, e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Fichiers de la bibliothèque (hors `.mecla`), avec leur taille et date de modification
+fn library_files(root: &Path) -> Result<Vec<(PathBuf, u64, i64)>> {
+    let mut files = Vec::new();
+    if !root.is_dir() {
+        return Ok(files);
+    }
+
+    let walker = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || e.file_name() != MECLA_DIR);
+    for entry in walker {
+        let entry = entry.with_context(|| format!("walk library {}", root.display()))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+        // Les liens symboliques (--mode symlink) ne sont pas indexés: leur cible est
+        // souvent dans le dépôt, et n'est pas un doublon d'elle-même
+        let Some((size, mtime)) = file_stamp(entry.path()) else {
+            continue;
+        };
+        let rel = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        files.push((rel.to_path_buf(), size, mtime));
+    }
+    Ok(files)
+}
+
+/// Taille et date de modification (ns) d'un fichier, None s'il n'existe pas ou
+/// s'il s'agit d'un lien symbolique
+fn file_stamp(path: &Path) -> Option<(u64, i64)> {
+    let md = fs::symlink_metadata(path).ok().filter(|m| m.is_file())?;
+    let mtime = md
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_nanos() as i64);
+    Some((md.len(), mtime))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("library");
+        fs::create_dir_all(root.join("2024")).unwrap();
+        fs::write(root.join("2024/a.jpg"), b"photo a").unwrap();
+        fs::write(root.join("2024/b.jpg"), b"photo b").unwrap();
+        (dir, root)
+    }
+
+    /// Chemin -> hash des entrées du fichier d'index
+    fn saved(root: &Path) -> HashMap<PathBuf, String> {
+        read_entries(&root.join(MECLA_DIR).join(INDEX_FILE))
+            .unwrap()
+            .into_iter()
+            .map(|(path, e)| (path, e.hash))
+            .collect()
+    }
+
+    #[test]
+    fn claims_each_content_once() {
+        let (_dir, root) = library();
+        let index = LibraryIndex::open(&root, false).unwrap();
+        let known = blake3::hash(b"photo a");
+        let new = blake3::hash(b"new photo");
+        let dest = root.join("2024/new.jpg");
+
+        assert!(index.contains(&known));
+        assert_eq!(index.claim(&known, &dest), Some(root.join("2024/a.jpg")));
+
+        assert!(!index.contains(&new));
+        assert_eq!(index.claim(&new, &dest), None);
+        assert!(index.contains(&new));
+        // Réservé par un autre worker: doublon du fichier en cours de transfert
+        assert_eq!(
+            index.claim(&new, &root.join("other.jpg")),
+            Some(dest.clone())
+        );
+
+        index.release(&new);
+        assert!(!index.contains(&new));
+        assert_eq!(index.claim(&new, &dest), None);
+    }
+
+    #[test]
+    fn deleted_library_file_is_no_longer_a_duplicate() {
+        let (_dir, root) = library();
+        let index = LibraryIndex::open(&root, false).unwrap();
+        fs::remove_file(root.join("2024/a.jpg")).unwrap();
+
+        let hash = blake3::hash(b"photo a");
+        assert!(!index.contains(&hash));
+        assert_eq!(index.claim(&hash, &root.join("2024/a2.jpg")), None);
+    }
+
+    #[test]
+    fn saves_claimed_files_that_were_written() {
+        let (_dir, root) = library();
+        let index = LibraryIndex::open(&root, false).unwrap();
+        let written = root.join("2024/c.jpg");
+        let failed = root.join("2024/d.jpg");
+        index.claim(&blake3::hash(b"photo c"), &written);
+        index.claim(&blake3::hash(b"photo d"), &failed);
+        fs::write(&written, b"photo c").unwrap();
+        index.save().unwrap();
+
+        let entries = saved(&root);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[Path::new("2024/c.jpg")],
+            blake3::hash(b"photo c").to_hex().as_str()
+        );
+        assert!(!entries.contains_key(Path::new("2024/d.jpg")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_indexed() {
+        let (dir, root) = library();
+        let depot = dir.path().join("depot.jpg");
+        fs::write(&depot, b"depot photo").unwrap();
+        std::os::unix::fs::symlink(&depot, root.join("2024/link.jpg")).unwrap();
+
+        let index = LibraryIndex::open(&root, false).unwrap();
+        assert!(index.contains(&blake3::hash(b"photo a")));
+        assert!(!index.contains(&blake3::hash(b"depot photo")));
+    }
+
+    #[test]
+    fn reopening_rehashes_modified_files_and_drops_deleted_ones() {
+        let (_dir, root) = library();
+        let index = LibraryIndex::open(&root, false).unwrap();
+        assert!(index.is_refreshed());
+        index.save().unwrap();
+
+        // Rien n'a changé: l'index enregistré est à jour
+        assert!(!LibraryIndex::open(&root, false).unwrap().is_refreshed());
+
+        fs::write(root.join("2024/a.jpg"), b"retouched photo a").unwrap();
+        fs::remove_file(root.join("2024/b.jpg")).unwrap();
+        let index = LibraryIndex::open(&root, false).unwrap();
+        assert!(index.is_refreshed());
+        assert!(index.contains(&blake3::hash(b"retouched photo a")));
+        assert!(!index.contains(&blake3::hash(b"photo a")));
+        assert!(!index.contains(&blake3::hash(b"photo b")));
+
+        index.save().unwrap();
+        let entries = saved(&root);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[Path::new("2024/a.jpg")],
+            blake3::hash(b"retouched photo a").to_hex().as_str()
+        );
+    }
+
+    #[test]
+    fn forget_removes_entries() {
+        let (_dir, root) = library();
+        LibraryIndex::open(&root, false).unwrap().save().unwrap();
+        forget(&root, &[root.join("2024/a.jpg")]).unwrap();
+        let entries = saved(&root);
+        assert_eq!(
+            entries.keys().collect::<Vec<_>>(),
+            [Path::new("2024/b.jpg")]
+        );
+    }
+}
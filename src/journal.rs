@@ -0,0 +1,493 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::{TransferMode, JOURNAL_DIR, MECLA_DIR, TRASH_DIR};
+use crate::filesystem::{blake3_file, move_or_copy};
+use crate::index;
+use crate::metadata::CaptureTime;
+
+/// Type d'opération journalisée
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalAction {
+    /// Déplacement vers la bibliothèque
+    Move,
+    /// Doublon mis à la corbeille (au lieu d'être supprimé)
+    Trash,
+    /// Copie dans la bibliothèque (source intacte)
+    Copy,
+    /// Lien dur dans la bibliothèque
+    Hardlink,
+    /// Lien symbolique dans la bibliothèque
+    Symlink,
+    /// Clone copy-on-write dans la bibliothèque
+    Reflink,
+}
+
+impl JournalAction {
+    /// Indique si l'opération a retiré le fichier de son emplacement d'origine
+    pub fn removed_source(self) -> bool {
+        matches!(self, JournalAction::Move | JournalAction::Trash)
+    }
+
+    /// Mode de transfert vers la bibliothèque (None pour la mise à la corbeille)
+    pub fn transfer_mode(self) -> Option<TransferMode> {
+        match self {
+            JournalAction::Move => Some(TransferMode::Move),
+            JournalAction::Copy => Some(TransferMode::Copy),
+            JournalAction::Hardlink => Some(TransferMode::Hardlink),
+            JournalAction::Symlink => Some(TransferMode::Symlink),
+            JournalAction::Reflink => Some(TransferMode::Reflink),
+            JournalAction::Trash => None,
+        }
+    }
+}
+
+impl From<TransferMode> for JournalAction {
+    fn from(mode: TransferMode) -> Self {
+        match mode {
+            TransferMode::Move => JournalAction::Move,
+            TransferMode::Copy => JournalAction::Copy,
+            TransferMode::Hardlink => JournalAction::Hardlink,
+            TransferMode::Symlink => JournalAction::Symlink,
+            TransferMode::Reflink => JournalAction::Reflink,
+        }
+    }
+}
+
+/// Entrée du journal (une ligne JSON par opération)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Horodatage RFC 3339 de l'écriture de l'entrée
+    pub ts: String,
+    pub action: JournalAction,
+    /// Emplacement d'origine du fichier
+    pub src: PathBuf,
+    /// Emplacement du fichier après l'opération (bibliothèque ou corbeille)
+    pub dest: PathBuf,
+    /// Hash BLAKE3 (hexadécimal) du contenu du fichier
+    pub hash: String,
+    /// Date de capture retenue (ISO 8601, avec le décalage UTC s'il est connu)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured: Option<String>,
+    /// Date de capture avant correction `--shift` (si une règle s'est appliquée)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_original: Option<String>,
+}
+
+/// Journal des opérations d'une exécution, en ajout seul.
+///
+/// Chaque entrée est écrite et synchronisée sur disque *avant* l'opération
+/// qu'elle décrit, ce qui permet `mecla undo` même après une interruption.
+/// Le fichier n'est créé qu'à la première opération.
+pub struct Journal {
+    path: PathBuf,
+    trash_dir: PathBuf,
+    input: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl Journal {
+    /// Prépare le journal d'une nouvelle exécution.
+    ///
+    /// # Arguments
+    /// * `output` - Racine de la bibliothèque (le journal va dans `output/.mecla/journal`)
+    /// * `input` - Racine du dépôt (pour construire les chemins de corbeille)
+    pub fn new(output: &Path, input: &Path) -> Self {
+        let run_id = format!(
+            "{}-{}",
+            Local::now().format("%Y%m%d-%H%M%S"),
+            std::process::id()
+        );
+        let mecla_dir = output.join(MECLA_DIR);
+
+        Self {
+            path: mecla_dir
+                .join(JOURNAL_DIR)
+                .join(format!("{}.jsonl", run_id)),
+            trash_dir: mecla_dir.join(TRASH_DIR).join(run_id),
+            input: input.to_path_buf(),
+            file: Mutex::new(None),
+        }
+    }
+
+    /// Chemin du fichier journal
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Indique si au moins une opération a été journalisée
+    pub fn is_empty(&self) -> bool {
+        self.file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none()
+    }
+
+    /// Emplacement dans la corbeille d'un fichier source (arborescence du dépôt conservée)
+    pub fn trash_path(&self, src: &Path) -> PathBuf {
+        let rel = src
+            .strip_prefix(&self.input)
+            .unwrap_or_else(|_| Path::new(src.file_name().unwrap_or(src.as_os_str())));
+        self.trash_dir.join(rel)
+    }
+
+    /// Écrit une entrée et la synchronise sur disque.
+    ///
+    /// # Errors
+    /// Retourne une erreur si le journal ne peut pas être créé ou écrit
+    pub fn record(
+        &self,
+        action: JournalAction,
+        src: &Path,
+        dest: &Path,
+        hash: &blake3::Hash,
+        capture: Option<&CaptureTime>,
+    ) -> Result<()> {
+        self.append(&JournalEntry {
+            ts: Local::now().to_rfc3339(),
+            action,
+            src: src.to_path_buf(),
+            dest: dest.to_path_buf(),
+            hash: hash.to_hex().to_string(),
+            captured: capture.map(CaptureTime::to_iso8601),
+            captured_original: capture.and_then(CaptureTime::original_iso8601),
+        })
+    }
+
+    /// Écrit une entrée déjà construite (ex: opération d'un plan) et la synchronise sur disque.
+    ///
+    /// # Errors
+    /// Retourne une erreur si le journal ne peut pas être créé ou écrit
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry).context("serialize journal entry")?;
+        line.push('\n');
+
+        let mut guard = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if guard.is_none() {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("create_dir_all {}", parent.display()))?;
+            }
+            let f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .with_context(|| format!("open journal {}", self.path.display()))?;
+            *guard = Some(f);
+        }
+
+        let f = guard.as_mut().expect("journal file just opened");
+        f.write_all(line.as_bytes())
+            .and_then(|_| f.sync_data())
+            .with_context(|| format!("write journal {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Lit toutes les entrées d'un journal
+///
+/// # Errors
+/// Retourne une erreur si le fichier est illisible ou contient une ligne invalide
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>> {
+    let f = File::open(path).with_context(|| format!("open journal {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (i, line) in BufReader::new(f).lines().enumerate() {
+        let line = line.with_context(|| format!("read journal {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_str(&line)
+            .with_context(|| format!("invalid journal entry at line {}", i + 1))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Annule les opérations d'un journal, de la plus récente à la plus ancienne.
+///
+/// Chaque fichier déplacé est remis à son emplacement d'origine s'il est toujours
+/// à sa destination avec le même contenu; les copies et liens créés sont supprimés.
+/// Les entrées dont l'opération n'a jamais eu lieu (interruption) sont ignorées.
+/// Les fichiers retirés de la bibliothèque sont aussi retirés de son index.
+///
+/// # Arguments
+/// * `path` - Fichier journal à rejouer à l'envers
+/// * `dry_run` - Si true, affiche les restaurations sans les réaliser
+///
+/// # Errors
+/// Retourne une erreur si le journal est illisible ou si des restaurations ont échoué
+pub fn undo(path: &Path, dry_run: bool) -> Result<()> {
+    let entries = read_journal(path)?;
+    let mut restored = 0;
+    let mut failed = 0;
+    // Fichiers retirés de la bibliothèque (la corbeille n'est pas indexée)
+    let mut removed = Vec::new();
+
+    for entry in entries.iter().rev() {
+        match undo_entry(entry, dry_run) {
+            Ok(true) => {
+                restored += 1;
+                if entry.action != JournalAction::Trash {
+                    removed.push(entry.dest.clone());
+                }
+            }
+            Ok(false) => {}
+            Err(e) => {
+                log::error!("{}: {:#}", entry.dest.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if !dry_run && !removed.is_empty() {
+        match library_root(path) {
+            Some(output) => {
+                if let Err(e) = index::forget(output, &removed) {
+                    log::error!("Unable to update the library index: {:#}", e);
+                }
+            }
+            None => log::warn!(
+                "Journal outside of a library, index not updated: {}",
+                path.display()
+            ),
+        }
+    }
+
+    println!("\n=== Undo ===");
+    println!("Operations reverted: {}", restored);
+    println!("Errors: {}", failed);
+
+    if failed > 0 {
+        bail!("{} file(s) could not be restored", failed);
+    }
+    Ok(())
+}
+
+/// Racine de la bibliothèque d'un journal (`output/.mecla/journal/<run>.jsonl`)
+fn library_root(journal: &Path) -> Option<&Path> {
+    let mecla_dir = journal.parent()?.parent()?;
+    if mecla_dir.file_name()? != MECLA_DIR {
+        return None;
+    }
+    mecla_dir.parent()
+}
+
+/// Annule une entrée du journal
+///
+/// # Returns
+/// true si l'opération a été annulée, false si l'entrée a été ignorée
+fn undo_entry(entry: &JournalEntry, dry_run: bool) -> Result<bool> {
+    if entry.action.removed_source() {
+        restore_moved(entry, dry_run)
+    } else {
+        remove_created(entry, dry_run)
+    }
+}
+
+/// Remet un fichier déplacé (bibliothèque ou corbeille) à son emplacement d'origine
+fn restore_moved(entry: &JournalEntry, dry_run: bool) -> Result<bool> {
+    if !entry.dest.exists() {
+        if entry.src.exists() {
+            log::info!(
+                "[UNDO-SKIP] operation never happened: {}",
+                entry.src.display()
+            );
+            return Ok(false);
+        }
+        bail!("file is neither at its destination nor at its origin");
+    }
+
+    if entry.src.exists() {
+        bail!(
+            "origin is occupied, not overwriting: {}",
+            entry.src.display()
+        );
+    }
+
+    let hash = blake3_file(&entry.dest).with_context(|| "hash dest")?;
+    if hash.to_hex().as_str() != entry.hash {
+        bail!("content changed since the run, not restoring");
+    }
+
+    log::info!("[UNDO] {} -> {}", entry.dest.display(), entry.src.display());
+    move_or_copy(&entry.dest, &entry.src, dry_run)?;
+    Ok(true)
+}
+
+/// Supprime la copie ou le lien créé dans la bibliothèque (la source n'a pas bougé)
+fn remove_created(entry: &JournalEntry, dry_run: bool) -> Result<bool> {
+    if fs::symlink_metadata(&entry.dest).is_err() {
+        log::info!(
+            "[UNDO-SKIP] operation never happened: {}",
+            entry.dest.display()
+        );
+        return Ok(false);
+    }
+
+    if entry.action == JournalAction::Symlink {
+        let target = fs::read_link(&entry.dest)
+            .with_context(|| format!("read_link {}", entry.dest.display()))?;
+        if target != entry.src {
+            bail!("symlink now points elsewhere, not removing");
+        }
+    } else {
+        let hash = blake3_file(&entry.dest).with_context(|| "hash dest")?;
+        if hash.to_hex().as_str() != entry.hash {
+            bail!("content changed since the run, not removing");
+        }
+    }
+
+    log::info!("[UNDO] remove {}", entry.dest.display());
+    if !dry_run {
+        fs::remove_file(&entry.dest).with_context(|| format!("remove {}", entry.dest.display()))?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::INDEX_FILE;
+    use crate::index::LibraryIndex;
+
+    /// Dépôt et bibliothèque dans un dossier temporaire
+    struct Dirs {
+        _tmp: tempfile::TempDir,
+        input: PathBuf,
+        output: PathBuf,
+    }
+
+    fn dirs() -> Dirs {
+        let tmp = tempfile::tempdir().unwrap();
+        let input = tmp.path().join("depot");
+        let output = tmp.path().join("library");
+        fs::create_dir_all(input.join("tag")).unwrap();
+        fs::create_dir_all(output.join("2024")).unwrap();
+        Dirs {
+            _tmp: tmp,
+            input,
+            output,
+        }
+    }
+
+    /// Journalise puis réalise une opération, comme `transfer_to_library`
+    fn run(journal: &Journal, action: JournalAction, src: &Path, dest: &Path) {
+        let hash = blake3_file(src).unwrap();
+        journal.record(action, src, dest, &hash, None).unwrap();
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+        if action.removed_source() {
+            fs::rename(src, dest).unwrap();
+        } else {
+            fs::copy(src, dest).unwrap();
+        }
+    }
+
+    #[test]
+    fn undo_restores_moved_files() {
+        let d = dirs();
+        let src = d.input.join("tag/IMG_0001.jpg");
+        let dest = d.output.join("2024/2024-05-01 10.00.00.jpg");
+        fs::write(&src, b"photo").unwrap();
+
+        let journal = Journal::new(&d.output, &d.input);
+        run(&journal, JournalAction::Move, &src, &dest);
+        assert!(!src.exists());
+
+        undo(journal.path(), false).unwrap();
+        assert_eq!(fs::read(&src).unwrap(), b"photo");
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn undo_restores_trashed_duplicates() {
+        let d = dirs();
+        let src = d.input.join("tag/IMG_0001.jpg");
+        fs::write(&src, b"photo").unwrap();
+
+        let journal = Journal::new(&d.output, &d.input);
+        let trash = journal.trash_path(&src);
+        // La corbeille garde l'arborescence du dépôt, sous .mecla/trash/<run>
+        assert!(trash.starts_with(d.output.join(MECLA_DIR).join(TRASH_DIR)));
+        assert!(trash.ends_with("tag/IMG_0001.jpg"));
+
+        run(&journal, JournalAction::Trash, &src, &trash);
+        undo(journal.path(), false).unwrap();
+        assert_eq!(fs::read(&src).unwrap(), b"photo");
+        assert!(!trash.exists());
+    }
+
+    #[test]
+    fn undo_removes_copies_and_leaves_the_source() {
+        let d = dirs();
+        let src = d.input.join("IMG_0001.jpg");
+        let dest = d.output.join("2024/2024-05-01 10.00.00.jpg");
+        fs::write(&src, b"photo").unwrap();
+
+        let journal = Journal::new(&d.output, &d.input);
+        run(&journal, JournalAction::Copy, &src, &dest);
+        undo(journal.path(), false).unwrap();
+        assert!(!dest.exists());
+        assert_eq!(fs::read(&src).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn undo_never_overwrites_a_reoccupied_origin() {
+        let d = dirs();
+        let src = d.input.join("IMG_0001.jpg");
+        let dest = d.output.join("2024/2024-05-01 10.00.00.jpg");
+        fs::write(&src, b"photo").unwrap();
+
+        let journal = Journal::new(&d.output, &d.input);
+        run(&journal, JournalAction::Move, &src, &dest);
+        fs::write(&src, b"new photo").unwrap();
+
+        assert!(undo(journal.path(), false).is_err());
+        assert_eq!(fs::read(&src).unwrap(), b"new photo");
+        assert_eq!(fs::read(&dest).unwrap(), b"photo");
+    }
+
+    #[test]
+    fn undo_skips_operations_that_never_happened() {
+        let d = dirs();
+        let src = d.input.join("IMG_0001.jpg");
+        fs::write(&src, b"photo").unwrap();
+
+        // Interruption entre l'écriture du journal et le déplacement
+        let journal = Journal::new(&d.output, &d.input);
+        let hash = blake3_file(&src).unwrap();
+        let dest = d.output.join("2024/a.jpg");
+        journal
+            .record(JournalAction::Move, &src, &dest, &hash, None)
+            .unwrap();
+        undo(journal.path(), false).unwrap();
+        assert!(src.exists());
+    }
+
+    #[test]
+    fn undo_removes_reverted_files_from_the_index() {
+        let d = dirs();
+        let src = d.input.join("IMG_0001.jpg");
+        let dest = d.output.join("2024/2024-05-01 10.00.00.jpg");
+        let kept = d.output.join("2024/kept.jpg");
+        fs::write(&src, b"photo").unwrap();
+        fs::write(&kept, b"kept").unwrap();
+
+        let journal = Journal::new(&d.output, &d.input);
+        run(&journal, JournalAction::Move, &src, &dest);
+        LibraryIndex::open(&d.output, false)
+            .unwrap()
+            .save()
+            .unwrap();
+
+        undo(journal.path(), false).unwrap();
+        let index = fs::read_to_string(d.output.join(MECLA_DIR).join(INDEX_FILE)).unwrap();
+        assert!(!index.contains("2024-05-01 10.00.00.jpg"), "{}", index);
+        assert!(index.contains("kept.jpg"), "{}", index);
+    }
+}
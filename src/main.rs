@@ -1,27 +1,60 @@
 mod config;
+mod config_file;
+mod exiftool;
 mod filesystem;
+mod grouping;
+mod index;
+mod journal;
+mod media;
 mod metadata;
+mod name_date;
 mod naming;
+mod native;
+mod perceptual;
+mod plan;
+mod plausible;
+mod provider;
+mod report;
+mod shift;
 mod stats;
+mod template;
+mod watcher;
 
 use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::Duration;
 use std::{fs, process};
 use walkdir::WalkDir;
 
-use config::{Args, Config, HASH_PREFIX_INCREMENT, HASH_PREFIX_INITIAL_LEN, HASH_PREFIX_MAX_LEN};
+use chrono::NaiveDateTime;
+use config::{
+    dry_run_setting, Args, Command, Config, MissingDatePolicy, NearDupPolicy, EXIFTOOL_BATCH_SIZE,
+    HASH_PREFIX_INCREMENT, HASH_PREFIX_INITIAL_LEN, HASH_PREFIX_MAX_LEN, MECLA_DIR,
+    NAME_COUNTER_MAX, NEAR_DUP_MAX_DISTANCE, QUARANTINE_DIR, REVIEW_DIR, UNDATED_DIR,
+};
 use filesystem::{
-    blake3_file, contains_supported_media, hash_prefix, is_dir_empty, is_supported, move_or_copy,
-    prune_empty_dirs_recursively,
+    blake3_file, contains_supported_media, hash_prefix, is_dir_empty, is_supported, links_to,
+    move_or_copy, prune_empty_dirs_recursively, rollback_group, transfer, Applied,
+    DestReservations, DryRunOverlay,
 };
-use metadata::{ensure_exiftool_available, extract_datetime_with_exiftool};
+use grouping::{group_by_stem, is_companion, pair_live_photos, LivePhotoPartners, MediaGroup};
+use index::LibraryIndex;
+use journal::{Journal, JournalAction};
+use media::MediaKind;
+use metadata::{resolve_capture_time, CaptureTime, DateOrigin, GpsPosition, MediaMetadata};
 use naming::{build_target_dir, format_filename, format_filename_with_suffix, infer_tag};
+use perceptual::{dhash, supports_dhash};
+use plan::{PlanEntry, PlanRecorder};
+use report::{Report, ReportAction, ReportRecord};
+use shift::apply_shift;
 use stats::Stats;
+use template::{NamingContext, Template};
+use watcher::WatchHandler;
 
 fn main() {
     // Initialiser le logger
@@ -36,68 +69,324 @@ fn main() {
 }
 
 fn run() -> Result<()> {
-    let args = Args::parse();
-    let cfg = Config::from_args(args)?;
+    let mut args = Args::parse();
+
+    match args.command.take() {
+        Some(Command::Undo { journal }) => journal::undo(&journal, dry_run_setting(&args)?),
+        Some(Command::Apply { plan }) => plan::apply(&plan, dry_run_setting(&args)?),
+        Some(Command::Plan { plan }) => {
+            // Rien n'est modifié pendant la planification
+            args.dry_run = true;
+            args.no_dry_run = false;
+            let cfg = Config::from_args(args)?;
+            write_plan(&cfg, &plan)
+        }
+        Some(Command::Watch {
+            settle,
+            prune_after,
+        }) => {
+            let cfg = Config::from_args(args)?;
+            watch(
+                &cfg,
+                Duration::from_secs(settle),
+                Duration::from_secs(prune_after),
+            )
+        }
+        None => {
+            let cfg = Config::from_args(args)?;
+            process(&cfg)
+        }
+    }
+}
+
+/// État partagé par les workers pendant une exécution
+struct RunContext {
+    stats: Stats,
+    /// Noms de destination en cours de traitement par les workers
+    reservations: DestReservations,
+    /// Journal des opérations (None en dry-run)
+    journal: Option<Journal>,
+    /// Fichiers créés et retirés par le dry-run (vide sinon)
+    overlay: DryRunOverlay,
+    /// Contenu de la bibliothèque, pour reconnaître les doublons sous un autre nom
+    index: LibraryIndex,
+    /// Rapport d'exécution (`--report`)
+    report: Option<Report>,
+    /// Opérations planifiées (`mecla plan`)
+    plan: Option<PlanRecorder>,
+    /// Live Photos classées, pour apparier les moitiés arrivées dans des lots différents
+    live_photos: LivePhotoPartners,
+}
+
+impl RunContext {
+    /// Prépare une exécution (journal, index de la bibliothèque)
+    fn new(cfg: &Config) -> Result<Self> {
+        let index = LibraryIndex::open(&cfg.output, cfg.near_dup != NearDupPolicy::Off)
+            .context("Unable to index the output library")?;
+        // Un aperçu ne modifie rien: la mise à jour de l'index reste en mémoire
+        if cfg.dry_run && index.is_refreshed() {
+            log::info!("Library index out of date, it will be saved by the next real run");
+        }
 
-    ensure_exiftool_available()?;
+        Ok(Self {
+            stats: Stats::new(),
+            reservations: DestReservations::new(),
+            journal: (!cfg.dry_run).then(|| Journal::new(&cfg.output, &cfg.input)),
+            overlay: DryRunOverlay::new(),
+            index,
+            report: cfg
+                .report
+                .as_ref()
+                .map(|(path, format)| Report::new(path, *format)),
+            plan: None,
+            live_photos: LivePhotoPartners::new(),
+        })
+    }
 
-    process(&cfg)
+    /// Écrit le rapport d'exécution, s'il est demandé
+    fn write_report(&self) -> Result<()> {
+        match &self.report {
+            Some(report) => report
+                .write()
+                .with_context(|| format!("Unable to write report {}", report.path().display())),
+            None => Ok(()),
+        }
+    }
 }
 
 fn process(cfg: &Config) -> Result<()> {
-    let stats = Stats::new();
+    let run = RunContext::new(cfg)?;
+
+    let files = collect_files(cfg);
+    if files.is_empty() {
+        log::info!("No supported files found in input directory");
+        return run.write_report();
+    }
+
+    let tags = sort_files(cfg, &run, files, atty::is(atty::Stream::Stdout));
+    run.write_report()?;
+
+    if !cfg.dry_run {
+        run.index
+            .save()
+            .context("Unable to save the library index")?;
+    }
+
+    // Nettoyage des dossiers TAG vides (seulement si les fichiers ont quitté le dépôt)
+    if cfg.mode.is_destructive() {
+        prune_empty_tag_dirs(cfg, &run.overlay, &tags)?;
+    }
+
+    // Afficher les statistiques
+    run.stats.print_summary();
+
+    if let Some(journal) = run.journal.as_ref().filter(|j| !j.is_empty()) {
+        println!(
+            "Journal: {} (revert with: mecla undo \"{}\")",
+            journal.path().display(),
+            journal.path().display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Calcule les opérations d'une exécution sans rien modifier, et les écrit dans un
+/// plan à relire avant `mecla apply`
+fn write_plan(cfg: &Config, path: &Path) -> Result<()> {
+    let mut run = RunContext::new(cfg)?;
+    run.plan = Some(PlanRecorder::new());
+
+    let files = collect_files(cfg);
+    if files.is_empty() {
+        log::info!("No supported files found in input directory");
+    }
+    sort_files(cfg, &run, files, atty::is(atty::Stream::Stdout));
+    run.write_report()?;
+
+    let recorder = run.plan.as_ref().expect("plan recorder");
+    let count = recorder
+        .write(path, &cfg.input, &cfg.output)
+        .with_context(|| format!("Unable to write plan {}", path.display()))?;
+
+    run.stats.print_summary();
+    println!(
+        "Plan: {} operations in {} (execute with: mecla apply \"{}\")",
+        count,
+        path.display(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Trie en continu les fichiers déposés dans le dépôt (`mecla watch`).
+///
+/// Les fichiers déjà présents sont classés au démarrage; chaque nouveau fichier
+/// l'est dès qu'il est stable. Toute la session partage un journal.
+fn watch(cfg: &Config, settle: Duration, prune_after: Duration) -> Result<()> {
+    let run = RunContext::new(cfg)?;
+    let mut handler = WatchSorter {
+        cfg,
+        run: &run,
+        tags: HashSet::new(),
+    };
+
+    let existing = collect_files(cfg);
+    if !existing.is_empty() {
+        handler.on_ready(existing);
+        handler.on_quiet();
+    }
+
+    log::info!("[WATCH] watching {}", cfg.input.display());
+    watcher::run(&cfg.input, settle, prune_after, &mut handler)
+}
+
+/// Classe les fichiers signalés par le watcher
+struct WatchSorter<'a> {
+    cfg: &'a Config,
+    run: &'a RunContext,
+    /// Dossiers TAG touchés depuis le dernier nettoyage
+    tags: HashSet<String>,
+}
+
+impl WatchHandler for WatchSorter<'_> {
+    fn accepts(&self, path: &Path) -> bool {
+        is_supported(path, &self.cfg.exts) || is_companion(path)
+    }
+
+    fn on_ready(&mut self, files: Vec<PathBuf>) {
+        let (processed, errors) = (self.run.stats.processed(), self.run.stats.errors());
+        log::info!("[WATCH] {} stable files to process", files.len());
+        self.tags
+            .extend(sort_files(self.cfg, self.run, files, false));
+        log::info!(
+            "[WATCH] processed: {}, errors: {}",
+            self.run.stats.processed() - processed,
+            self.run.stats.errors() - errors
+        );
+
+        if !self.cfg.dry_run {
+            if let Err(e) = self.run.index.save() {
+                log::error!("Unable to save the library index: {:#}", e);
+            }
+        }
+        if let Err(e) = self.run.write_report() {
+            log::error!("{:#}", e);
+        }
+    }
+
+    fn on_quiet(&mut self) {
+        let tags = std::mem::take(&mut self.tags);
+        if self.cfg.mode.is_destructive() {
+            if let Err(e) = prune_empty_tag_dirs(self.cfg, &self.run.overlay, &tags) {
+                log::error!("{:#}", e);
+            }
+        }
+    }
+}
 
-    // Collecter tous les fichiers à traiter
-    let files: Vec<PathBuf> = WalkDir::new(&cfg.input)
+/// Liste les fichiers à traiter du dépôt (médias supportés et leurs compagnons)
+fn collect_files(cfg: &Config) -> Vec<PathBuf> {
+    WalkDir::new(&cfg.input)
         .follow_links(false)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| !e.file_type().is_dir())
         .map(|e| e.path().to_path_buf())
-        .filter(|p| is_supported(p, &cfg.exts))
-        .collect();
+        .filter(|p| is_supported(p, &cfg.exts) || is_companion(p))
+        .collect()
+}
 
-    if files.is_empty() {
-        log::info!("No supported files found in input directory");
-        return Ok(());
+/// Classe des fichiers du dépôt: regroupement, lecture des métadonnées, puis
+/// traitement parallèle groupe par groupe.
+///
+/// # Arguments
+/// * `run` - État partagé de l'exécution (stats, réservations, journal, index)
+/// * `files` - Fichiers supportés et compagnons à classer
+/// * `progress` - Affiche une barre de progression
+///
+/// # Returns
+/// Les dossiers TAG des fichiers classés (pour le nettoyage)
+fn sort_files(
+    cfg: &Config,
+    run: &RunContext,
+    files: Vec<PathBuf>,
+    progress: bool,
+) -> HashSet<String> {
+    // Un média et ses sidecars/RAW de même nom sont classés ensemble
+    let groups = group_by_stem(files, &cfg.exts);
+    let total: usize = groups.iter().map(MediaGroup::len).sum();
+
+    if groups.is_empty() {
+        return HashSet::new();
     }
 
-    log::info!("Found {} files to process", files.len());
+    log::info!("Found {} files to process", total);
 
     // Créer la barre de progression (seulement si stdout est un terminal)
-    let pb = if atty::is(atty::Stream::Stdout) {
-        let pb = ProgressBar::new(files.len() as u64);
+    let pb = progress.then(|| {
+        let pb = ProgressBar::new(total as u64);
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
                 .unwrap()
                 .progress_chars("=>-"),
         );
-        Some(pb)
-    } else {
-        None
-    };
+        pb
+    });
 
     // Tags vus (pour le nettoyage final)
     let tags_seen = Mutex::new(HashSet::new());
 
-    // Traitement parallèle des fichiers
-    files.par_iter().for_each(|src| {
-        match handle_one(cfg, src, &stats) {
-            Ok(tag) => {
-                if let Some(t) = tag {
-                    tags_seen.lock().unwrap().insert(t);
+    // Lecture parallèle des métadonnées, par lots de groupes d'un même dossier
+    // (une seule requête par lot). Tout est lu avant le classement pour pouvoir
+    // apparier les Live Photos réparties dans plusieurs dossiers.
+    if let Some(ref pb) = pb {
+        pb.set_message("reading metadata");
+    }
+    let metas: HashMap<PathBuf, MediaMetadata> =
+        batch_by_directory(&groups, rayon::current_num_threads())
+            .par_iter()
+            .flat_map_iter(|chunk| {
+                let leaders: Vec<PathBuf> = chunk.iter().map(|g| g.leader.clone()).collect();
+                cfg.metadata.extract_batch(&leaders)
+            })
+            .collect();
+    if let Some(ref pb) = pb {
+        pb.set_message("");
+    }
+
+    let groups = pair_live_photos(groups, &metas);
+
+    // Traitement parallèle, groupe par groupe
+    groups.par_iter().for_each(|group| {
+        match handle_group(cfg, run, group, metas.get(&group.leader)) {
+            Ok(origin) => {
+                run.stats.add_date_origin(origin, group.len());
+                let mut tags = tags_seen.lock().unwrap();
+                for member in group.members() {
+                    if let Some(t) = infer_tag(&cfg.input, member) {
+                        tags.insert(t);
+                    }
+                    run.stats.inc_processed();
                 }
-                stats.inc_processed();
             }
             Err(e) => {
-                log::error!("{}: {:#}", src.display(), e);
-                stats.inc_errors();
+                log::error!("{}: {:#}", group.leader.display(), e);
+                run.stats.add_errors(group.len());
+                if let Some(report) = &run.report {
+                    report.add(
+                        group
+                            .members()
+                            .map(|src| ReportRecord::failed(src, &e))
+                            .collect(),
+                    );
+                }
             }
         }
 
         if let Some(ref pb) = pb {
-            pb.inc(1);
+            pb.inc(group.len() as u64);
         }
     });
 
@@ -105,92 +394,557 @@ fn process(cfg: &Config) -> Result<()> {
         pb.finish_with_message("Done");
     }
 
-    // Nettoyage des dossiers TAG vides
-    let tags = tags_seen.into_inner().unwrap();
-    prune_empty_tag_dirs(cfg, &tags)?;
-
-    // Afficher les statistiques
-    stats.print_summary();
-
-    Ok(())
+    tags_seen.into_inner().unwrap()
 }
 
-/// Traite un fichier individuel
+/// Regroupe les groupes de fichiers par dossier parent, puis découpe chaque dossier en lots.
+///
+/// La taille des lots est bornée par `EXIFTOOL_BATCH_SIZE`, et réduite pour les petits
+/// dossiers afin que tous les workers aient du travail.
+///
+/// # Arguments
+/// * `groups` - Groupes de fichiers à traiter
+/// * `workers` - Nombre de workers parallèles
 ///
 /// # Returns
-/// Le tag du fichier (si présent) pour le nettoyage ultérieur
-fn handle_one(cfg: &Config, src: &Path, stats: &Stats) -> Result<Option<String>> {
-    let tag = infer_tag(&cfg.input, src);
+/// Les lots de groupes, chacun issu d'un seul dossier
+fn batch_by_directory(groups: &[MediaGroup], workers: usize) -> Vec<Vec<MediaGroup>> {
+    let mut by_dir: BTreeMap<&Path, Vec<MediaGroup>> = BTreeMap::new();
+    for g in groups {
+        by_dir
+            .entry(g.leader.parent().unwrap_or(Path::new("")))
+            .or_default()
+            .push(g.clone());
+    }
 
-    let dt = extract_datetime_with_exiftool(src)
-        .with_context(|| "Unable to extract a date via exiftool or mtime")?;
+    let mut chunks = Vec::new();
+    for (_, dir_files) in by_dir {
+        let size = dir_files
+            .len()
+            .div_ceil(workers.max(1))
+            .clamp(1, EXIFTOOL_BATCH_SIZE);
+        chunks.extend(dir_files.chunks(size).map(|c| c.to_vec()));
+    }
+    chunks
+}
 
-    let ext = src
-        .extension()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| anyhow!("File without extension: {}", src.display()))?
-        .to_lowercase();
+/// Membre d'un groupe en cours de traitement
+struct Member<'a> {
+    src: &'a Path,
+    /// Extension conservée dans le nom de destination (ex: `jpg`, `cr2.xmp`)
+    ext: String,
+    hash: blake3::Hash,
+    size: u64,
+}
 
-    let target_dir = build_target_dir(&cfg.output, &dt, tag.as_deref());
-    let base_name = format_filename(&dt, &ext);
-    let mut dest = target_dir.join(&base_name);
+/// Sort d'un membre pour un nom de destination donné
+enum Placement {
+    /// Destination libre
+    Free(PathBuf),
+    /// Contenu déjà présent dans la bibliothèque (fichier existant)
+    Duplicate(PathBuf),
+}
 
-    // S'il n'y a pas de conflit, on déplace direct.
-    if !dest.exists() {
-        move_or_copy(src, &dest, cfg.dry_run)?;
-        return Ok(tag);
+impl Placement {
+    fn dest(&self) -> &Path {
+        match self {
+            Placement::Free(dest) | Placement::Duplicate(dest) => dest,
+        }
     }
+}
+
+/// Variante du nom de base essayée en cas de collision
+#[derive(Clone, Copy)]
+enum NameVariant {
+    Base,
+    Counter(u32),
+    HashSuffix(usize),
+}
+
+/// Traite un groupe de fichiers (média principal et compagnons)
+///
+/// La date et le nom de base viennent du fichier principal; chaque membre garde
+/// son extension. En cas de collision, le même suffixe est appliqué à tout le groupe.
+///
+/// # Arguments
+/// * `run` - État partagé de l'exécution (stats, réservations, journal)
+/// * `group` - Fichiers à classer ensemble
+/// * `meta` - Métadonnées lues pour le fichier principal (si disponibles)
+///
+/// # Returns
+/// La provenance de la date du groupe (None: sans date)
+fn handle_group(
+    cfg: &Config,
+    run: &RunContext,
+    group: &MediaGroup,
+    meta: Option<&MediaMetadata>,
+) -> Result<Option<DateOrigin>> {
+    let leader = &group.leader;
+    let tag = infer_tag(&cfg.input, leader);
 
-    // Conflit: comparer hashes
-    log::warn!("[CONFLICT] {} -> {}", src.display(), dest.display());
+    let kind = MediaKind::from_path(leader);
+    let gps = meta.and_then(|m| m.gps.as_ref());
+    let capture = resolve_capture_time(leader, meta, &cfg.date_policy())
+        .with_context(|| "Unable to extract a date from metadata or mtime")?
+        .map(|c| apply_shift(leader, &cfg.shifts, c, tag.as_deref(), meta));
+    let origin = capture.as_ref().map(|c| c.origin);
 
-    let src_hash = blake3_file(src).with_context(|| "hash source")?;
-    let dst_hash = blake3_file(&dest).with_context(|| "hash dest")?;
+    let members = group
+        .members()
+        .map(|src| {
+            let ext = group
+                .member_ext(src)
+                .ok_or_else(|| anyhow!("File without extension: {}", src.display()))?;
+            let hash =
+                blake3_file(src).with_context(|| format!("hash source {}", src.display()))?;
+            let size = fs::metadata(src)
+                .with_context(|| format!("stat source {}", src.display()))?
+                .len();
+            Ok(Member {
+                src,
+                ext,
+                hash,
+                size,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    if src_hash == dst_hash {
-        // Identique: skip + supprimer source
+    if capture.is_none() && cfg.on_missing_date == MissingDatePolicy::Skip {
         log::info!(
-            "[SKIP-DUP] same hash, delete source: {}",
-            src.display()
+            "[SKIP] no capture date, left in input: {}",
+            leader.display()
         );
-        if !cfg.dry_run {
-            fs::remove_file(src).with_context(|| "delete source (dup)")?;
+        report_skipped(run, &members, None, gps);
+        return Ok(origin);
+    }
+
+    let ctx = NamingContext {
+        // Sans date, le nom d'origine est conservé ({stem}): la date n'est pas utilisée
+        dt: capture
+            .as_ref()
+            .map_or_else(NaiveDateTime::default, |c| c.local),
+        sub_sec: capture.as_ref().and_then(|c| c.sub_sec),
+        tag: tag.as_deref(),
+        make: meta.and_then(|m| m.make.as_deref()),
+        model: meta.and_then(|m| m.model.as_deref()),
+        kind: kind.as_str(),
+        stem: &group.stem,
+        hash: Some(&members[0].hash),
+        counter: None,
+    };
+
+    let (mut target_dir, name) = match &capture {
+        Some(c) if cfg.undated_tree && c.origin != DateOrigin::Metadata => (
+            build_target_dir(&cfg.output.join(UNDATED_DIR), &cfg.layout, &ctx),
+            &cfg.name,
+        ),
+        Some(_) => (build_target_dir(&cfg.output, &cfg.layout, &ctx), &cfg.name),
+        // Sans date: dossiers du dépôt et nom d'origine, en quarantaine ou sous Undated/
+        None => {
+            let base = match cfg.on_missing_date {
+                MissingDatePolicy::Quarantine => cfg.output.join(MECLA_DIR).join(QUARANTINE_DIR),
+                _ => cfg.output.join(UNDATED_DIR),
+            };
+            let rel = leader
+                .parent()
+                .and_then(|p| p.strip_prefix(&cfg.input).ok())
+                .unwrap_or(Path::new(""));
+            log::warn!(
+                "[UNDATED] no capture date, keeping original name: {}",
+                leader.display()
+            );
+            (base.join(rel), &cfg.undated_name)
+        }
+    };
+
+    // Moitié d'une Live Photo dont l'autre moitié a été classée par un lot précédent
+    // (`mecla watch`): même dossier et même nom de base qu'elle
+    let live_id = meta.and_then(|m| m.content_identifier.as_deref());
+    let partner = live_id.and_then(|id| run.live_photos.partner(id, leader));
+    let partner_stem = partner
+        .as_ref()
+        .and_then(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().into_owned());
+    let partner_name;
+    let (ctx, name) = match (&partner, &partner_stem) {
+        (Some(partner), Some(stem)) => {
+            log::debug!(
+                "[LIVE] {} paired with {}",
+                leader.display(),
+                partner.display()
+            );
+            target_dir = partner.parent().unwrap_or(&cfg.output).to_path_buf();
+            partner_name = Template::parse_name("{stem}")?;
+            (NamingContext { stem, ..ctx }, &partner_name)
+        }
+        _ => (ctx, name),
+    };
+
+    // Copie recompressée ou redimensionnée d'une image de la bibliothèque
+    let (leader_dhash, near_dup) = find_near_duplicate(cfg, run, leader, &members[0].hash);
+    if near_dup {
+        run.stats.add_near_duplicates(members.len());
+        match cfg.near_dup {
+            NearDupPolicy::Skip => {
+                log::info!("[SKIP] near-duplicate left in input: {}", leader.display());
+                report_skipped(run, &members, capture.as_ref(), gps);
+                return Ok(origin);
+            }
+            NearDupPolicy::Review => {
+                let rel = target_dir.strip_prefix(&cfg.output).unwrap_or(&target_dir);
+                target_dir = cfg.output.join(MECLA_DIR).join(REVIEW_DIR).join(rel);
+            }
+            NearDupPolicy::Report | NearDupPolicy::Off => {}
+        }
+    }
+
+    // Réserve le nom de base (sans extension) jusqu'à la fin du traitement: un autre
+    // worker visant le même nom (même seconde) attend, et voit donc nos fichiers une
+    // fois déplacés. Les noms suffixés dérivent du nom de base et sont couverts par
+    // la même réservation.
+    let _reservation = run
+        .reservations
+        .reserve(&target_dir.join(name.render(&ctx)));
+
+    let (variant, mut placements) = place_group(&run.overlay, name, &target_dir, &ctx, &members)?;
+
+    // Un contenu déjà présent ailleurs dans la bibliothèque est un doublon, quel que
+    // soit son nom
+    let mut claimed = Vec::new();
+    for (member, placement) in members.iter().zip(placements.iter_mut()) {
+        let Placement::Free(dest) = placement else {
+            continue;
+        };
+        match run.index.claim(&member.hash, dest) {
+            Some(existing) => {
+                log::info!(
+                    "[INDEX] {} same content as {}",
+                    member.src.display(),
+                    existing.display()
+                );
+                *placement = Placement::Duplicate(existing);
+            }
+            None => claimed.push(member.hash),
+        }
+    }
+
+    // Référence pour les images suivantes, sauf si elle est mise en revue
+    let reviewed = near_dup && cfg.near_dup == NearDupPolicy::Review;
+    if let Some(d) = leader_dhash.filter(|_| !reviewed) {
+        run.index.set_dhash(&members[0].hash, d);
+    }
+    if !matches!(variant, NameVariant::Base) {
+        if let Some(Placement::Free(dest)) = placements.first() {
+            log::info!("[RENAME] dest exists diff hash, using: {}", dest.display());
         }
-        stats.inc_duplicates();
-        return Ok(tag);
     }
 
-    // Différent: on cherche un nom suffixé libre
-    let mut n = HASH_PREFIX_INITIAL_LEN;
-    loop {
-        let suffix = hash_prefix(&src_hash, n);
-        let alt_name = format_filename_with_suffix(&dt, &suffix, &ext);
-        let alt_dest = target_dir.join(&alt_name);
+    let leader_dest = placements[0].dest().to_path_buf();
+
+    // Les membres sont transférés ensemble: en cas d'échec, ceux déjà transférés
+    // sont remis en place
+    let renamed = !matches!(variant, NameVariant::Base);
+    let mut done = Vec::new();
+    let mut records = Vec::new();
+    let mut planned = Vec::new();
+    for (member, placement) in members.iter().zip(placements) {
+        // Un lien de la bibliothèque vers la source n'en est pas une copie: la source
+        // est conservée, sans quoi le lien serait rompu
+        let trash = match &placement {
+            Placement::Duplicate(existing) => {
+                cfg.mode.is_destructive() && !links_to(existing, member.src)
+            }
+            Placement::Free(_) => false,
+        };
+        let (dest, action) = match &placement {
+            Placement::Free(dest) if renamed => (dest, ReportAction::Renamed),
+            Placement::Free(dest) => (dest, cfg.mode.into()),
+            Placement::Duplicate(existing) if trash => (existing, ReportAction::DuplicateDeleted),
+            Placement::Duplicate(existing) => (existing, ReportAction::DuplicateKept),
+        };
+        records.push(ReportRecord::done(
+            member.src,
+            Some(dest),
+            action,
+            capture.as_ref(),
+            &member.hash,
+            member.size,
+            gps,
+        ));
+        let planned_action = match &placement {
+            Placement::Free(_) => Some(cfg.mode.into()),
+            Placement::Duplicate(_) if trash => Some(JournalAction::Trash),
+            Placement::Duplicate(_) => None,
+        };
+        if let Some(action) = planned_action {
+            planned.push(PlanEntry::new(
+                action,
+                member.src,
+                dest,
+                &member.hash,
+                member.size,
+                capture.as_ref(),
+            ));
+        }
 
-        if !alt_dest.exists() {
-            log::info!(
-                "[RENAME] dest exists diff hash, using: {}",
-                alt_dest.display()
+        let result = match placement {
+            Placement::Free(dest) => {
+                transfer_to_library(cfg, run, member.src, &dest, &member.hash, capture.as_ref())
+                    .map(|_| {
+                        if renamed {
+                            run.stats.inc_renamed();
+                        }
+                        Some(Applied {
+                            src: member.src,
+                            dest,
+                            removed_source: cfg.mode.is_destructive(),
+                        })
+                    })
+            }
+            Placement::Duplicate(_) => trash_duplicate(cfg, run, member, trash, capture.as_ref()),
+        };
+
+        match result {
+            Ok(Some(applied)) => done.push(applied),
+            Ok(None) => {}
+            Err(e) => {
+                if !cfg.dry_run {
+                    rollback_group(&done);
+                }
+                for hash in &claimed {
+                    run.index.release(hash);
+                }
+                return Err(e.context(format!("transfer {}", member.src.display())));
+            }
+        }
+    }
+
+    if let Some(id) = live_id {
+        run.live_photos.record(id, &leader_dest);
+    }
+    if let Some(report) = &run.report {
+        report.add(records);
+    }
+    if let Some(plan) = &run.plan {
+        plan.add(planned);
+    }
+    Ok(origin)
+}
+
+/// Ajoute au rapport les membres d'un groupe laissés dans le dépôt
+fn report_skipped(
+    run: &RunContext,
+    members: &[Member],
+    capture: Option<&CaptureTime>,
+    gps: Option<&GpsPosition>,
+) {
+    if let Some(report) = &run.report {
+        report.add(
+            members
+                .iter()
+                .map(|m| {
+                    let action = ReportAction::Skipped;
+                    ReportRecord::done(m.src, None, action, capture, &m.hash, m.size, gps)
+                })
+                .collect(),
+        );
+    }
+}
+
+/// Cherche une image proche du fichier principal dans la bibliothèque (`--near-dup`).
+///
+/// # Returns
+/// Le hash perceptuel du fichier (s'il a été calculé) et si un quasi-doublon a été trouvé
+fn find_near_duplicate(
+    cfg: &Config,
+    run: &RunContext,
+    leader: &Path,
+    hash: &blake3::Hash,
+) -> (Option<u64>, bool) {
+    // Un doublon exact est traité comme tel, pas comme un quasi-doublon
+    if cfg.near_dup == NearDupPolicy::Off || !supports_dhash(leader) || run.index.contains(hash) {
+        return (None, false);
+    }
+
+    let d = match dhash(leader) {
+        Ok(d) => d,
+        Err(e) => {
+            log::debug!("no perceptual hash for {}: {:#}", leader.display(), e);
+            return (None, false);
+        }
+    };
+
+    match run.index.find_similar(d, NEAR_DUP_MAX_DISTANCE) {
+        Some((similar, distance)) => {
+            log::warn!(
+                "[NEAR-DUP] {} looks like {} (distance {})",
+                leader.display(),
+                similar.display(),
+                distance
             );
+            (Some(d), true)
+        }
+        None => (Some(d), false),
+    }
+}
+
+/// Cherche un nom de base sous lequel aucun membre du groupe n'écrase un fichier
+/// différent, ni un autre membre du groupe.
+///
+/// Essaie le nom de base, puis `{counter}` si le template l'utilise, sinon un
+/// préfixe du hash du fichier principal de longueur croissante.
+///
+/// # Returns
+/// La variante retenue et, pour chaque membre, sa destination
+fn place_group(
+    overlay: &DryRunOverlay,
+    name: &Template,
+    target_dir: &Path,
+    ctx: &NamingContext,
+    members: &[Member],
+) -> Result<(NameVariant, Vec<Placement>)> {
+    let mut variants = vec![NameVariant::Base];
+    if name.uses_counter() {
+        variants.extend((1..=NAME_COUNTER_MAX).map(NameVariant::Counter));
+    } else {
+        variants.extend(
+            (HASH_PREFIX_INITIAL_LEN..=HASH_PREFIX_MAX_LEN)
+                .step_by(HASH_PREFIX_INCREMENT)
+                .map(NameVariant::HashSuffix),
+        );
+    }
+
+    'variants: for variant in variants {
+        let mut placements: Vec<Placement> = Vec::with_capacity(members.len());
+        for member in members {
+            let dest = variant_dest(name, target_dir, ctx, member, variant);
+            // Deux membres visant le même fichier: le second écraserait le premier
+            if placements.iter().any(|p| p.dest() == dest) {
+                continue 'variants;
+            }
+            if !overlay.exists(&dest) {
+                placements.push(Placement::Free(dest));
+                continue;
+            }
+
+            if matches!(variant, NameVariant::Base) {
+                log::warn!("[CONFLICT] {} -> {}", member.src.display(), dest.display());
+            }
+            let dst_hash = overlay.hash(&dest).with_context(|| "hash dest")?;
+            if dst_hash != member.hash {
+                continue 'variants;
+            }
+            placements.push(Placement::Duplicate(dest));
+        }
+        return Ok((variant, placements));
+    }
+
+    bail!("Persistent collision…")
+}
 
-            dest = alt_dest;
-            stats.inc_renamed();
-            break;
+/// Destination d'un membre pour une variante du nom de base
+fn variant_dest(
+    name: &Template,
+    target_dir: &Path,
+    ctx: &NamingContext,
+    member: &Member,
+    variant: NameVariant,
+) -> PathBuf {
+    let file_name = match variant {
+        NameVariant::Base => format_filename(name, ctx, &member.ext),
+        NameVariant::Counter(counter) => {
+            let ctx = NamingContext {
+                counter: Some(counter),
+                ..ctx.clone()
+            };
+            format_filename(name, &ctx, &member.ext)
+        }
+        NameVariant::HashSuffix(n) => {
+            // Le suffixe vient du fichier principal: tout le groupe garde le même nom
+            let suffix = hash_prefix(ctx.hash.expect("group hash"), n);
+            format_filename_with_suffix(name, ctx, &suffix, &member.ext)
         }
+    };
+    target_dir.join(file_name)
+}
 
-        // si collision, on augmente la longueur du prefix
-        if n >= HASH_PREFIX_MAX_LEN {
-            bail!("Persistent collision…");
+/// Traite un membre déjà présent dans la bibliothèque
+///
+/// # Arguments
+/// * `trash` - Met la source à la corbeille (sinon elle est laissée en place)
+///
+/// # Returns
+/// La mise à la corbeille réalisée, ou None si la source est laissée en place
+fn trash_duplicate<'a>(
+    cfg: &Config,
+    run: &RunContext,
+    member: &Member<'a>,
+    trash: bool,
+    capture: Option<&CaptureTime>,
+) -> Result<Option<Applied<'a>>> {
+    let src = member.src;
+    run.stats.inc_duplicates();
+
+    // Modes non destructifs (ou lien vers la source): le dépôt n'est pas modifié
+    if !trash {
+        log::info!(
+            "[SKIP-DUP] same hash, source left untouched: {}",
+            src.display()
+        );
+        return Ok(None);
+    }
+
+    // Identique: skip + mettre la source à la corbeille (récupérable via undo)
+    log::info!("[SKIP-DUP] same hash, delete source: {}", src.display());
+    match &run.journal {
+        Some(journal) => {
+            let trash = journal.trash_path(src);
+            journal.record(JournalAction::Trash, src, &trash, &member.hash, capture)?;
+            move_or_copy(src, &trash, cfg.dry_run).with_context(|| "delete source (dup)")?;
+            Ok(Some(Applied {
+                src,
+                dest: trash,
+                removed_source: true,
+            }))
+        }
+        // Dry-run: pas de journal ni de corbeille
+        None => {
+            run.overlay.remove(src);
+            Ok(None)
         }
-        n += HASH_PREFIX_INCREMENT;
     }
+}
+
+/// Journalise l'opération puis transfère le fichier selon `--mode`
+fn transfer_to_library(
+    cfg: &Config,
+    run: &RunContext,
+    src: &Path,
+    dest: &Path,
+    hash: &blake3::Hash,
+    capture: Option<&CaptureTime>,
+) -> Result<()> {
+    if let Some(journal) = &run.journal {
+        journal.record(cfg.mode.into(), src, dest, hash, capture)?;
+    }
+    transfer(src, dest, cfg.mode, cfg.dry_run)?;
 
-    move_or_copy(src, &dest, cfg.dry_run)?;
-    Ok(tag)
+    // Les fichiers suivants doivent voir la destination occupée, comme sur disque
+    if cfg.dry_run {
+        run.overlay.create(dest, hash);
+        if cfg.mode.is_destructive() {
+            run.overlay.remove(src);
+        }
+    }
+    Ok(())
 }
 
 /// Nettoie les dossiers TAG vides après traitement
-fn prune_empty_tag_dirs(cfg: &Config, tags_seen: &HashSet<String>) -> Result<()> {
+fn prune_empty_tag_dirs(
+    cfg: &Config,
+    overlay: &DryRunOverlay,
+    tags_seen: &HashSet<String>,
+) -> Result<()> {
     for tag in tags_seen {
         let tag_dir = cfg.input.join(tag);
         if !tag_dir.is_dir() {
@@ -198,7 +952,7 @@ fn prune_empty_tag_dirs(cfg: &Config, tags_seen: &HashSet<String>) -> Result<()>
         }
 
         // S'il reste encore des médias supportés sous ce TAG, on ne touche pas.
-        if contains_supported_media(&tag_dir, &cfg.exts) {
+        if contains_supported_media(&tag_dir, &cfg.exts, overlay) {
             continue;
         }
 
@@ -222,3 +976,355 @@ fn prune_empty_tag_dirs(cfg: &Config, tags_seen: &HashSet<String>) -> Result<()>
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::DEFAULT_NAME;
+
+    fn member<'a>(src: &'a Path, content: &[u8]) -> Member<'a> {
+        Member {
+            src,
+            ext: "jpg".to_string(),
+            hash: blake3::hash(content),
+            size: content.len() as u64,
+        }
+    }
+
+    fn context(hash: &blake3::Hash) -> NamingContext<'_> {
+        NamingContext {
+            dt: NaiveDateTime::parse_from_str("2024-05-01 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap(),
+            kind: "photo",
+            stem: "IMG_0001",
+            hash: Some(hash),
+            ..Default::default()
+        }
+    }
+
+    fn dests(placements: &[Placement]) -> Vec<(&'static str, &Path)> {
+        placements
+            .iter()
+            .map(|p| match p {
+                Placement::Free(d) => ("free", d.as_path()),
+                Placement::Duplicate(d) => ("duplicate", d.as_path()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn collision_never_overwrites_a_different_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = Template::parse_name(DEFAULT_NAME).unwrap();
+        let base = dir.path().join("2024-05-01 10.00.00.jpg");
+        let src = Path::new("IMG_0001.jpg");
+        let members = [member(src, b"photo")];
+        let ctx = context(&members[0].hash);
+        let overlay = DryRunOverlay::new();
+
+        let (variant, placements) =
+            place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert!(matches!(variant, NameVariant::Base));
+        assert_eq!(dests(&placements), [("free", base.as_path())]);
+
+        // Même contenu: doublon du fichier existant
+        fs::write(&base, b"photo").unwrap();
+        let (variant, placements) =
+            place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert!(matches!(variant, NameVariant::Base));
+        assert_eq!(dests(&placements), [("duplicate", base.as_path())]);
+
+        // Contenu différent: nom suffixé par le hash du fichier principal
+        fs::write(&base, b"other").unwrap();
+        let (variant, placements) =
+            place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert!(matches!(
+            variant,
+            NameVariant::HashSuffix(HASH_PREFIX_INITIAL_LEN)
+        ));
+        let suffixed = dir.path().join(format!(
+            "2024-05-01 10.00.00 {}.jpg",
+            hash_prefix(&members[0].hash, HASH_PREFIX_INITIAL_LEN)
+        ));
+        assert_eq!(dests(&placements), [("free", suffixed.as_path())]);
+    }
+
+    #[test]
+    fn collision_tries_the_counter_when_the_template_uses_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = Template::parse_name("{year}-{month}-{day}[_{counter}]").unwrap();
+        let members = [member(Path::new("IMG_0001.jpg"), b"photo")];
+        let ctx = context(&members[0].hash);
+        fs::write(dir.path().join("2024-05-01.jpg"), b"a").unwrap();
+        fs::write(dir.path().join("2024-05-01_1.jpg"), b"b").unwrap();
+
+        let (variant, placements) =
+            place_group(&DryRunOverlay::new(), &name, dir.path(), &ctx, &members).unwrap();
+        assert!(matches!(variant, NameVariant::Counter(2)));
+        let dest = dir.path().join("2024-05-01_2.jpg");
+        assert_eq!(dests(&placements), [("free", dest.as_path())]);
+    }
+
+    #[test]
+    fn group_members_share_the_variant() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = Template::parse_name(DEFAULT_NAME).unwrap();
+        let raw = Member {
+            ext: "cr2".to_string(),
+            ..member(Path::new("IMG_0001.cr2"), b"raw")
+        };
+        let xmp = Member {
+            ext: "xmp".to_string(),
+            ..member(Path::new("IMG_0001.xmp"), b"xmp")
+        };
+        let members = [raw, xmp];
+        let ctx = context(&members[0].hash);
+        let suffix = hash_prefix(&members[0].hash, HASH_PREFIX_INITIAL_LEN);
+
+        // Seul le sidecar entre en collision: tout le groupe prend le suffixe
+        fs::write(dir.path().join("2024-05-01 10.00.00.xmp"), b"other").unwrap();
+        let (_, placements) =
+            place_group(&DryRunOverlay::new(), &name, dir.path(), &ctx, &members).unwrap();
+        let raw_dest = dir
+            .path()
+            .join(format!("2024-05-01 10.00.00 {}.cr2", suffix));
+        let xmp_dest = dir
+            .path()
+            .join(format!("2024-05-01 10.00.00 {}.xmp", suffix));
+        assert_eq!(
+            dests(&placements),
+            [("free", raw_dest.as_path()), ("free", xmp_dest.as_path())]
+        );
+    }
+
+    #[test]
+    fn group_members_never_share_a_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = Template::parse_name(DEFAULT_NAME).unwrap();
+        let members = [
+            member(Path::new("IMG_0001.JPG"), b"photo"),
+            member(Path::new("IMG_0001.jpg"), b"other"),
+        ];
+        let ctx = context(&members[0].hash);
+
+        // Même extension pour les deux membres: aucune variante ne les sépare
+        let err = place_group(&DryRunOverlay::new(), &name, dir.path(), &ctx, &members)
+            .err()
+            .expect("two members on one destination");
+        assert!(
+            err.to_string().contains("Persistent collision"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn placement_sees_simulated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let name = Template::parse_name(DEFAULT_NAME).unwrap();
+        let base = dir.path().join("2024-05-01 10.00.00.jpg");
+        let members = [member(Path::new("IMG_0001.jpg"), b"photo")];
+        let ctx = context(&members[0].hash);
+        let overlay = DryRunOverlay::new();
+
+        // Fichier créé par le dry-run, même contenu: doublon
+        overlay.create(&base, &members[0].hash);
+        let (_, placements) = place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert_eq!(dests(&placements), [("duplicate", base.as_path())]);
+
+        // Contenu différent: renommé, sans que rien ne soit écrit
+        overlay.create(&base, &blake3::hash(b"other"));
+        let (variant, _) = place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert!(matches!(variant, NameVariant::HashSuffix(_)));
+        assert!(!base.exists());
+
+        // Fichier du disque retiré par le dry-run: le nom est de nouveau libre
+        fs::write(&base, b"other").unwrap();
+        overlay.remove(&base);
+        let (_, placements) = place_group(&overlay, &name, dir.path(), &ctx, &members).unwrap();
+        assert_eq!(dests(&placements), [("free", base.as_path())]);
+    }
+
+    /// Classe `input/trip/<nom>` avec le backend de fixtures (date des métadonnées
+    /// si donnée), en mode `move` par défaut
+    fn sort_fixture(
+        dir: &Path,
+        flags: &[&str],
+        files: &[(&str, Option<&str>)],
+    ) -> (Config, RunContext) {
+        let entries = files
+            .iter()
+            .map(|(name, date)| {
+                let mut entry = serde_json::json!({ "SourceFile": name });
+                if let Some(date) = date {
+                    entry["ExifIFD:DateTimeOriginal"] = (*date).into();
+                }
+                entry
+            })
+            .collect();
+        let cfg = fixture_config(dir, flags, entries);
+        let run = RunContext::new(&cfg).unwrap();
+        sort_files(&cfg, &run, collect_files(&cfg), false);
+        (cfg, run)
+    }
+
+    /// Crée les fichiers `input/trip/<SourceFile>` des fixtures, et la configuration
+    /// qui les lit (fuseau UTC)
+    fn fixture_config(dir: &Path, flags: &[&str], mut entries: Vec<serde_json::Value>) -> Config {
+        let trip = dir.join("input").join("trip");
+        fs::create_dir_all(&trip).unwrap();
+        for entry in &mut entries {
+            let name = entry["SourceFile"].as_str().unwrap().to_string();
+            fs::write(trip.join(&name), name.as_bytes()).unwrap();
+            entry["SourceFile"] = format!("trip/{}", name).into();
+        }
+        let fixture = dir.join("fixture.json");
+        fs::write(&fixture, serde_json::Value::from(entries).to_string()).unwrap();
+        let config = dir.join("config.toml");
+        fs::write(&config, "").unwrap();
+
+        let (input, output) = (dir.join("input"), dir.join("output"));
+        let mut argv = vec!["mecla", "--config", config.to_str().unwrap()];
+        argv.extend(["--input", input.to_str().unwrap()]);
+        argv.extend(["--output", output.to_str().unwrap()]);
+        argv.extend(["--metadata-backend", "fixture"]);
+        argv.extend(["--metadata-fixture", fixture.to_str().unwrap()]);
+        argv.extend(["--timezone", "UTC"]);
+        argv.extend(flags);
+        Config::from_args(Args::parse_from(argv)).unwrap()
+    }
+
+    fn count(counter: &std::sync::atomic::AtomicUsize) -> usize {
+        counter.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    #[test]
+    fn missing_date_policies_route_undated_files() {
+        let files = [
+            ("IMG_0001.jpg", Some("2024:05:01 10:00:00")),
+            ("scan.jpg", None),
+        ];
+        let cases = [
+            ("skip", None),
+            ("quarantine", Some(".mecla/quarantine/trip/scan.jpg")),
+            ("unknown-dir", Some("Undated/trip/scan.jpg")),
+        ];
+        for (policy, dest) in cases {
+            let dir = tempfile::tempdir().unwrap();
+            let (cfg, run) = sort_fixture(dir.path(), &["--on-missing-date", policy], &files);
+
+            let dated = cfg.output.join("2024/05 trip/2024-05-01 10.00.00.jpg");
+            assert!(dated.is_file(), "{}", policy);
+            let src = cfg.input.join("trip/scan.jpg");
+            match dest {
+                // Sans date: laissé dans le dépôt
+                None => assert!(src.is_file(), "{}", policy),
+                Some(dest) => {
+                    assert!(!src.exists(), "{}", policy);
+                    assert!(cfg.output.join(dest).is_file(), "{}", policy);
+                }
+            }
+
+            assert_eq!(count(&run.stats.processed), 2, "{}", policy);
+            assert_eq!(count(&run.stats.dates_metadata), 1, "{}", policy);
+            assert_eq!(count(&run.stats.undated), 1, "{}", policy);
+            assert_eq!(count(&run.stats.errors), 0, "{}", policy);
+        }
+    }
+
+    #[test]
+    fn dry_run_writes_nothing_in_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = dir.path().join("output/2024/05");
+        fs::create_dir_all(&library).unwrap();
+        fs::write(library.join("photo.jpg"), b"photo").unwrap();
+
+        let files = [("IMG_0001.jpg", Some("2024:05:01 10:00:00"))];
+        let (cfg, run) = sort_fixture(dir.path(), &["--dry-run"], &files);
+        assert_eq!(count(&run.stats.processed), 1);
+        // Index à mettre à jour, mais pas enregistré
+        assert!(run.index.is_refreshed());
+        assert!(!cfg.output.join(MECLA_DIR).exists());
+        assert!(cfg.input.join("trip/IMG_0001.jpg").is_file());
+    }
+
+    #[test]
+    fn move_keeps_sources_linked_from_the_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [("IMG_0001.jpg", Some("2024:05:01 10:00:00"))];
+        let (cfg, _) = sort_fixture(dir.path(), &["--mode", "symlink"], &files);
+        let link = cfg.output.join("2024/05 trip/2024-05-01 10.00.00.jpg");
+        let src = cfg.input.join("trip/IMG_0001.jpg");
+        assert!(links_to(&link, &src));
+
+        // Le lien est la source elle-même: ni doublon à jeter, ni lien rompu
+        let (_, run) = sort_fixture(dir.path(), &["--mode", "move"], &files);
+        assert_eq!(count(&run.stats.duplicates), 1);
+        assert!(src.is_file());
+        assert_eq!(fs::read(&link).unwrap(), b"IMG_0001.jpg");
+        assert!(!cfg.output.join(MECLA_DIR).join(config::TRASH_DIR).exists());
+    }
+
+    #[test]
+    fn live_photo_halves_sorted_in_different_batches_stay_together() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = fixture_config(
+            dir.path(),
+            &[],
+            vec![
+                serde_json::json!({
+                    "SourceFile": "IMG_0001.heic",
+                    "ExifIFD:DateTimeOriginal": "2024:05:01 10:00:00",
+                    "Apple:ContentIdentifier": "LIVE-1",
+                }),
+                serde_json::json!({
+                    "SourceFile": "IMG_0001.mov",
+                    "QuickTime:CreateDate": "2024:05:01 08:00:01",
+                    "Keys:ContentIdentifier": "LIVE-1",
+                }),
+            ],
+        );
+        let run = RunContext::new(&cfg).unwrap();
+
+        // Lots successifs de `mecla watch`: la photo, puis sa vidéo
+        let trip = cfg.input.join("trip");
+        sort_files(&cfg, &run, vec![trip.join("IMG_0001.heic")], false);
+        sort_files(&cfg, &run, vec![trip.join("IMG_0001.mov")], false);
+
+        let dir = cfg.output.join("2024/05 trip");
+        assert!(dir.join("2024-05-01 10.00.00.heic").is_file());
+        assert!(dir.join("2024-05-01 10.00.00.mov").is_file());
+        assert_eq!(count(&run.stats.processed), 2);
+    }
+
+    #[test]
+    fn undated_tree_files_dates_without_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("IMG_0001.jpg", Some("2024:05:01 10:00:00")),
+            ("IMG-20240502-WA0001.jpg", None),
+            ("scan.jpg", None),
+        ];
+        // Date de modification du fichier sans date (mtime, politique par défaut)
+        let trip = dir.path().join("input").join("trip");
+        fs::create_dir_all(&trip).unwrap();
+        let scan = fs::File::create(trip.join("scan.jpg")).unwrap();
+        let mtime = chrono::DateTime::parse_from_rfc3339("2023-06-01T12:00:00Z").unwrap();
+        scan.set_modified(mtime.into()).unwrap();
+        drop(scan);
+
+        let (cfg, run) = sort_fixture(dir.path(), &["--undated-tree"], &files[..2]);
+        for dest in [
+            "2024/05 trip/2024-05-01 10.00.00.jpg",
+            "Undated/2024/05 trip/2024-05-02 00.00.00.jpg",
+            "Undated/2023/06 trip/2023-06-01 12.00.00.jpg",
+        ] {
+            assert!(cfg.output.join(dest).is_file(), "{}", dest);
+        }
+
+        assert_eq!(count(&run.stats.processed), 3);
+        assert_eq!(count(&run.stats.dates_metadata), 1);
+        assert_eq!(count(&run.stats.dates_filename), 1);
+        assert_eq!(count(&run.stats.dates_mtime), 1);
+        assert_eq!(count(&run.stats.undated), 0);
+    }
+}
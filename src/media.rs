@@ -0,0 +1,192 @@
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Famille de formats, qui détermine l'ordre de lecture des dates et si le
+/// format est traité par défaut
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Photo,
+    /// Fichier RAW d'appareil photo
+    Raw,
+    Video,
+    Audio,
+}
+
+/// Registre des formats connus, dans l'ordre de préférence du fichier principal
+/// d'un groupe (le JPEG d'une paire RAW+JPEG, la photo d'un couple photo+vidéo)
+pub const MEDIA_FORMATS: &[(&str, MediaKind)] = &[
+    ("jpg", MediaKind::Photo),
+    ("jpeg", MediaKind::Photo),
+    ("png", MediaKind::Photo),
+    ("heic", MediaKind::Photo),
+    ("heif", MediaKind::Photo),
+    ("gif", MediaKind::Photo),
+    ("tif", MediaKind::Photo),
+    ("tiff", MediaKind::Photo),
+    ("mpo", MediaKind::Photo),
+    ("cr2", MediaKind::Raw),
+    ("cr3", MediaKind::Raw),
+    ("nef", MediaKind::Raw),
+    ("arw", MediaKind::Raw),
+    ("dng", MediaKind::Raw),
+    ("raf", MediaKind::Raw),
+    ("orf", MediaKind::Raw),
+    ("rw2", MediaKind::Raw),
+    ("mp4", MediaKind::Video),
+    ("mov", MediaKind::Video),
+    ("m4v", MediaKind::Video),
+    ("avi", MediaKind::Video),
+    ("mkv", MediaKind::Video),
+    ("3gp", MediaKind::Video),
+    ("m4a", MediaKind::Audio),
+    ("mp3", MediaKind::Audio),
+    ("wav", MediaKind::Audio),
+    ("aac", MediaKind::Audio),
+    ("flac", MediaKind::Audio),
+];
+
+impl MediaKind {
+    /// Famille d'une extension (en minuscules, sans le point)
+    pub fn from_ext(ext: &str) -> Option<Self> {
+        MEDIA_FORMATS
+            .iter()
+            .find(|(e, _)| *e == ext)
+            .map(|(_, kind)| *kind)
+    }
+
+    /// Famille d'un fichier, d'après son extension. Une extension absente du
+    /// registre (ajoutée avec `--ext`) est traitée comme une photo.
+    pub fn from_path(path: &Path) -> Self {
+        path.extension()
+            .and_then(OsStr::to_str)
+            .and_then(|e| Self::from_ext(&e.to_lowercase()))
+            .unwrap_or(MediaKind::Photo)
+    }
+
+    /// Nom de la famille (placeholder `{kind}`)
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Photo => "photo",
+            MediaKind::Raw => "raw",
+            MediaKind::Video => "video",
+            MediaKind::Audio => "audio",
+        }
+    }
+
+    /// Indique si les formats de la famille sont traités sans `--ext`
+    pub fn enabled_by_default(self) -> bool {
+        // Les enregistrements audio (mémos vocaux…) ne sont classés que sur demande
+        self != MediaKind::Audio
+    }
+
+    /// Ordre de priorité des tags de date (nom du tag, sans le groupe).
+    ///
+    /// Les photos et RAW privilégient les dates EXIF, écrites en heure locale par
+    /// l'appareil. Les vidéos et enregistrements audio privilégient `CreationDate`
+    /// (clés QuickTime des iPhone, avec décalage UTC), puis les dates des en-têtes
+    /// QuickTime, en UTC.
+    pub fn date_tags(self) -> &'static [&'static str] {
+        match self {
+            MediaKind::Photo => &[
+                "DateTimeOriginal",
+                "CreateDate",
+                "CreationDate",
+                "DateCreated",
+                "ModifyDate",
+            ],
+            // ModifyDate est souvent la date de conversion (DNG), en dernier recours
+            MediaKind::Raw => &["DateTimeOriginal", "CreateDate", "ModifyDate"],
+            MediaKind::Video => &[
+                "CreationDate",
+                "DateTimeOriginal",
+                "CreateDate",
+                "MediaCreateDate",
+                "TrackCreateDate",
+                "ModifyDate",
+            ],
+            MediaKind::Audio => &[
+                "CreationDate",
+                "CreateDate",
+                "MediaCreateDate",
+                "TrackCreateDate",
+                "DateTimeOriginal",
+                "ModifyDate",
+            ],
+        }
+    }
+}
+
+/// Extensions traitées par défaut (familles activées par défaut)
+pub fn default_extensions() -> Vec<String> {
+    MEDIA_FORMATS
+        .iter()
+        .filter(|(_, kind)| kind.enabled_by_default())
+        .map(|(ext, _)| ext.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions_map_to_their_kind() {
+        for ext in ["cr2", "cr3", "nef", "arw", "dng", "raf", "orf", "rw2"] {
+            assert_eq!(MediaKind::from_ext(ext), Some(MediaKind::Raw), "{}", ext);
+        }
+        assert_eq!(MediaKind::from_ext("heic"), Some(MediaKind::Photo));
+        assert_eq!(MediaKind::from_ext("mov"), Some(MediaKind::Video));
+        assert_eq!(MediaKind::from_ext("m4a"), Some(MediaKind::Audio));
+        assert_eq!(MediaKind::from_ext("xmp"), None);
+
+        // Extension en majuscules, ou hors du registre (ajoutée avec --ext)
+        assert_eq!(
+            MediaKind::from_path(Path::new("IMG_0001.NEF")),
+            MediaKind::Raw
+        );
+        assert_eq!(
+            MediaKind::from_path(Path::new("scan.webp")),
+            MediaKind::Photo
+        );
+        assert_eq!(MediaKind::from_path(Path::new("README")), MediaKind::Photo);
+    }
+
+    #[test]
+    fn date_tags_depend_on_the_kind() {
+        // EXIF d'abord pour les photos, clés QuickTime d'abord pour les vidéos
+        assert_eq!(MediaKind::Photo.date_tags()[0], "DateTimeOriginal");
+        assert_eq!(MediaKind::Raw.date_tags()[0], "DateTimeOriginal");
+        assert_eq!(MediaKind::Video.date_tags()[0], "CreationDate");
+        assert_eq!(MediaKind::Audio.date_tags()[0], "CreationDate");
+        assert_ne!(MediaKind::Photo.date_tags(), MediaKind::Raw.date_tags());
+        assert_ne!(MediaKind::Video.date_tags(), MediaKind::Audio.date_tags());
+
+        // ModifyDate en dernier recours partout
+        for kind in [
+            MediaKind::Photo,
+            MediaKind::Raw,
+            MediaKind::Video,
+            MediaKind::Audio,
+        ] {
+            assert_eq!(kind.date_tags().last(), Some(&"ModifyDate"), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn audio_is_opt_in() {
+        assert!(MediaKind::Photo.enabled_by_default());
+        assert!(MediaKind::Raw.enabled_by_default());
+        assert!(MediaKind::Video.enabled_by_default());
+        assert!(!MediaKind::Audio.enabled_by_default());
+
+        let exts = default_extensions();
+        for (ext, kind) in MEDIA_FORMATS {
+            assert_eq!(
+                exts.contains(&ext.to_string()),
+                *kind != MediaKind::Audio,
+                "{}",
+                ext
+            );
+        }
+    }
+}
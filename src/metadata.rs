@@ -1,9 +1,305 @@
 use anyhow::{bail, Context, Result};
-use chrono::NaiveDateTime;
-use std::path::Path;
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, Offset, TimeZone, Timelike};
+use chrono_tz::Tz;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::SystemTime;
 
+use crate::config::MissingDatePolicy;
+use crate::exiftool::{has_line_break, ExiftoolPool};
+use crate::media::MediaKind;
+use crate::name_date::{date_from_filename, date_from_folder, FilenameDates};
+use crate::plausible::PlausibleDates;
+
+/// Groupes EXIF auxquels s'appliquent les tags SubSecTime* et OffsetTime*
+const EXIF_GROUPS: &[&str] = &["ExifIFD", "IFD0"];
+
+/// Groupes dont les dates sont en UTC (en-têtes QuickTime `mvhd`, `tkhd`, `mdhd`)
+const UTC_DATE_GROUPS: &[&str] = &["QuickTime", "Track"];
+
+/// Groupes exiftool ignorés pour les dates (dates du système de fichiers, pas de capture)
+const IGNORED_DATE_GROUPS: &[&str] = &["System", "File", "ExifTool", "Composite"];
+
+/// Tag de date lu dans les métadonnées
+#[derive(Debug, Clone)]
+pub struct DateTag {
+    /// Nom complet du tag, préfixé par son groupe (ex: `ExifIFD:DateTimeOriginal`)
+    pub tag: String,
+    /// Valeur brute renvoyée par exiftool
+    pub raw: String,
+    /// Date/heure interprétée (telle qu'écrite, sans conversion de fuseau)
+    pub value: NaiveDateTime,
+    /// Décalage UTC indiqué dans la valeur elle-même (ex: `+02:00`)
+    pub offset: Option<FixedOffset>,
+    /// Fraction de seconde (nanosecondes) écrite dans la valeur, même nulle
+    pub sub_sec: Option<u32>,
+}
+
+impl DateTag {
+    /// Crée un tag de date à partir de sa valeur brute (None si elle n'est pas une date valide)
+    pub fn parse(tag: &str, raw: String) -> Option<Self> {
+        let (value, offset, sub_sec) = parse_exif_datetime(&raw)?;
+        Some(Self {
+            tag: tag.to_string(),
+            raw,
+            value,
+            offset,
+            sub_sec,
+        })
+    }
+
+    /// Groupe du tag (ex: `ExifIFD`)
+    pub fn group(&self) -> &str {
+        self.tag.split_once(':').map_or("", |(g, _)| g)
+    }
+
+    /// Indique si la valeur est en UTC (dates QuickTime sans décalage explicite)
+    fn is_utc(&self) -> bool {
+        let group = self.group().trim_end_matches(|c: char| c.is_ascii_digit());
+        self.offset.is_none() && UTC_DATE_GROUPS.contains(&group)
+    }
+
+    /// Nom du tag sans le groupe (ex: `DateTimeOriginal`)
+    pub fn name(&self) -> &str {
+        self.tag.rsplit(':').next().unwrap_or(&self.tag)
+    }
+}
+
+/// Provenance de la date de capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrigin {
+    /// Tag de date des métadonnées
+    Metadata,
+    /// Nom du fichier
+    Filename,
+    /// Nom d'un dossier du dépôt
+    Folder,
+    /// Date de modification du fichier
+    Mtime,
+}
+
+/// Date de capture retenue pour un fichier
+#[derive(Debug, Clone)]
+pub struct CaptureTime {
+    /// Heure locale de la prise de vue (utilisée pour le classement et le nommage)
+    pub local: NaiveDateTime,
+    /// Décalage de l'heure locale par rapport à UTC, s'il est connu
+    pub offset: Option<FixedOffset>,
+    /// Origine de la date: tag de métadonnées (ex: `ExifIFD:DateTimeOriginal`),
+    /// `filename`, `folder` ou `mtime`
+    pub source: String,
+    pub origin: DateOrigin,
+    /// Fraction de seconde lue dans les métadonnées (nanosecondes), même nulle;
+    /// None si aucune fraction n'est connue
+    pub sub_sec: Option<u32>,
+    /// Heure lue avant correction de l'horloge de l'appareil (`--shift`)
+    pub original: Option<NaiveDateTime>,
+}
+
+impl CaptureTime {
+    /// Date au format ISO 8601, avec le décalage UTC s'il est connu
+    pub fn to_iso8601(&self) -> String {
+        format_iso8601(&self.local, self.offset)
+    }
+
+    /// Date d'origine (avant `--shift`) au format ISO 8601, si elle a été corrigée
+    pub fn original_iso8601(&self) -> Option<String> {
+        self.original.map(|o| format_iso8601(&o, self.offset))
+    }
+}
+
+/// Formate une heure locale en ISO 8601, avec son décalage UTC s'il est connu
+fn format_iso8601(local: &NaiveDateTime, offset: Option<FixedOffset>) -> String {
+    match offset.and_then(|o| local.and_local_timezone(o).single()) {
+        Some(dt) => dt.format("%Y-%m-%dT%H:%M:%S%.f%:z").to_string(),
+        None => local.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+    }
+}
+
+/// Position GPS (degrés décimaux, altitude en mètres)
+#[derive(Debug, Clone, Copy)]
+pub struct GpsPosition {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+/// Métadonnées d'un fichier média, telles que lues par un `MetadataProvider`
+#[derive(Debug, Clone, Default)]
+pub struct MediaMetadata {
+    /// Tous les tags de date trouvés. La priorité vient de `MediaKind::date_tags`; à nom
+    /// égal, le premier l'emporte (exiftool: clés `Groupe:Tag` par ordre alphabétique)
+    pub dates: Vec<DateTag>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub gps: Option<GpsPosition>,
+    pub sub_sec_time_original: Option<String>,
+    pub sub_sec_time: Option<String>,
+    pub sub_sec_time_digitized: Option<String>,
+    pub offset_time_original: Option<String>,
+    pub offset_time: Option<String>,
+    pub offset_time_digitized: Option<String>,
+    /// Identifiant commun à la photo et à la vidéo d'une Live Photo Apple
+    pub content_identifier: Option<String>,
+}
+
+impl MediaMetadata {
+    /// Construit les métadonnées à partir d'un objet JSON exiftool (`-json -G1 -n`)
+    fn from_json(obj: &serde_json::Map<String, Value>) -> Self {
+        let mut meta = MediaMetadata::default();
+        // Coordonnées GPS: les valeurs `Composite:` sont signées; les autres (`GPS:`
+        // avec `-n`) ne le sont pas, l'hémisphère est dans le tag `…Ref`
+        let mut composite = HashMap::new();
+        let mut unsigned = HashMap::new();
+        let mut refs = HashMap::new();
+
+        for (key, value) in obj {
+            let (group, name) = match key.split_once(':') {
+                Some((g, n)) => (g, n),
+                None => continue, // SourceFile
+            };
+
+            let text = match value_to_string(value) {
+                Some(t) => t,
+                None => continue,
+            };
+
+            match name {
+                "Make" => meta.make = Some(text),
+                "Model" => meta.model = Some(text),
+                "GPSLatitude" | "GPSLongitude" | "GPSAltitude" => {
+                    let coords = match group {
+                        "Composite" => &mut composite,
+                        _ => &mut unsigned,
+                    };
+                    if let Some(v) = value.as_f64() {
+                        coords.entry(name).or_insert(v);
+                    }
+                }
+                "GPSLatitudeRef" | "GPSLongitudeRef" | "GPSAltitudeRef" => {
+                    refs.insert(name.trim_end_matches("Ref"), text);
+                }
+                "SubSecTimeOriginal" => meta.sub_sec_time_original = Some(text),
+                "SubSecTime" => meta.sub_sec_time = Some(text),
+                "SubSecTimeDigitized" => meta.sub_sec_time_digitized = Some(text),
+                "OffsetTimeOriginal" => meta.offset_time_original = Some(text),
+                "OffsetTime" => meta.offset_time = Some(text),
+                "OffsetTimeDigitized" => meta.offset_time_digitized = Some(text),
+                "ContentIdentifier" | "MediaGroupUUID" => meta.content_identifier = Some(text),
+                _ => {
+                    if IGNORED_DATE_GROUPS.contains(&group) {
+                        continue;
+                    }
+                    if let Some(d) = DateTag::parse(key, text) {
+                        meta.dates.push(d);
+                    }
+                }
+            }
+        }
+
+        // Sud, ouest et sous le niveau de la mer (`GPSAltitudeRef` = 1) sont négatifs
+        let coord = |name: &str, negative: &str| {
+            composite.get(name).copied().or_else(|| {
+                let v = *unsigned.get(name)?;
+                Some(match refs.get(name) {
+                    Some(r) if r == negative => -v.abs(),
+                    _ => v,
+                })
+            })
+        };
+        let alt = coord("GPSAltitude", "1");
+        if let (Some(latitude), Some(longitude)) =
+            (coord("GPSLatitude", "S"), coord("GPSLongitude", "W"))
+        {
+            meta.gps = Some(GpsPosition {
+                latitude,
+                longitude,
+                altitude: alt,
+            });
+        }
+
+        meta
+    }
+
+    /// Retourne le tag de date de capture le plus prioritaire pour la famille du
+    /// fichier (voir `MediaKind::date_tags`), parmi ceux dont la date est plausible
+    pub fn capture_date(&self, kind: MediaKind, plausible: &PlausibleDates) -> Option<&DateTag> {
+        self.date_candidates(kind)
+            .find(|d| plausible.accepts(d.value))
+    }
+
+    /// Tags de date de capture, dans l'ordre de priorité de la famille du fichier
+    fn date_candidates(&self, kind: MediaKind) -> impl Iterator<Item = &DateTag> {
+        kind.date_tags()
+            .iter()
+            .flat_map(|name| self.dates.iter().filter(move |d| d.name() == *name))
+    }
+
+    /// Retourne la date de capture, en heure locale de la prise de vue.
+    ///
+    /// Les dates EXIF n'ont qu'une précision à la seconde et pas de fuseau: la fraction
+    /// et le décalage sont lus dans les tags SubSecTime* / OffsetTime* correspondants
+    /// (`…Original` pour `DateTimeOriginal`, `…Digitized` pour `CreateDate`, sans
+    /// suffixe pour `ModifyDate`), sauf si la valeur du tag en contient déjà.
+    /// Les dates QuickTime sont en UTC et converties dans le fuseau `tz`.
+    ///
+    /// # Arguments
+    /// * `kind` - Famille du fichier (ordre de priorité des tags de date)
+    /// * `tz` - Fuseau de conversion des dates UTC (None = fuseau du système)
+    /// * `plausible` - Filtre des dates invraisemblables (tag suivant si rejetée)
+    pub fn capture_time(
+        &self,
+        kind: MediaKind,
+        tz: Option<Tz>,
+        plausible: &PlausibleDates,
+    ) -> Option<CaptureTime> {
+        let date = self.capture_date(kind, plausible)?;
+        let source = date.tag.clone();
+
+        if date.is_utc() {
+            let (local, offset) = utc_to_local(date.value, tz);
+            return Some(CaptureTime {
+                local,
+                offset: Some(offset),
+                source,
+                origin: DateOrigin::Metadata,
+                sub_sec: date.sub_sec,
+                original: None,
+            });
+        }
+
+        let mut local = date.value;
+        let mut offset = date.offset;
+        let mut sub_sec = date.sub_sec;
+
+        if EXIF_GROUPS.contains(&date.group()) {
+            let (sub_sec_tag, offset_time) = match date.name() {
+                "DateTimeOriginal" => (&self.sub_sec_time_original, &self.offset_time_original),
+                "CreateDate" => (&self.sub_sec_time_digitized, &self.offset_time_digitized),
+                "ModifyDate" => (&self.sub_sec_time, &self.offset_time),
+                _ => (&None, &None),
+            };
+            if sub_sec.is_none() {
+                sub_sec = sub_sec_tag.as_deref().and_then(parse_sub_seconds);
+                local = local.with_nanosecond(sub_sec.unwrap_or(0)).unwrap_or(local);
+            }
+            offset = offset.or_else(|| offset_time.as_deref().and_then(parse_utc_offset));
+        }
+
+        Some(CaptureTime {
+            local,
+            offset,
+            source,
+            origin: DateOrigin::Metadata,
+            sub_sec,
+            original: None,
+        })
+    }
+}
+
 /// Vérifie qu'exiftool est disponible sur le système
 pub fn ensure_exiftool_available() -> Result<()> {
     let out = Command::new("exiftool")
@@ -17,91 +313,380 @@ pub fn ensure_exiftool_available() -> Result<()> {
     Ok(())
 }
 
-/// Extrait la date/heure d'un fichier média via exiftool.
+/// Extrait les métadonnées d'un lot de fichiers en une seule requête exiftool.
 ///
-/// Tente d'abord d'extraire les métadonnées EXIF/QuickTime via exiftool.
-/// En cas d'échec, utilise la date de modification du fichier comme fallback.
+/// Si la requête échoue (exiftool bloqué ou planté sur un fichier corrompu), les
+/// fichiers du lot sont relus un par un: seul le fichier fautif reste sans
+/// métadonnées. Les fichiers absents du résultat (exiftool en erreur, format non
+/// reconnu) n'ont simplement pas d'entrée dans la table retournée.
+///
+/// # Arguments
+/// * `exiftool` - Pool de processus exiftool persistants
+/// * `paths` - Fichiers à analyser (idéalement d'un même dossier)
+///
+/// # Returns
+/// Les métadonnées de chaque fichier reconnu, indexées par chemin
+pub fn extract_metadata_batch_with_exiftool(
+    exiftool: &ExiftoolPool,
+    paths: &[PathBuf],
+) -> HashMap<PathBuf, MediaMetadata> {
+    match try_exiftool_batch(exiftool, paths) {
+        Ok(map) => map,
+        Err(e) if paths.len() > 1 => {
+            log::warn!(
+                "exiftool batch failed ({} files in {}), retrying one file at a time: {:#}",
+                paths.len(),
+                paths
+                    .first()
+                    .and_then(|p| p.parent())
+                    .unwrap_or(Path::new(""))
+                    .display(),
+                e
+            );
+            paths
+                .iter()
+                .flat_map(|p| {
+                    extract_metadata_batch_with_exiftool(exiftool, std::slice::from_ref(p))
+                })
+                .collect()
+        }
+        Err(e) => {
+            log::warn!("exiftool failed on {}: {:#}", paths[0].display(), e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Règles de datation des fichiers (voir `resolve_capture_time`)
+pub struct DatePolicy<'a> {
+    /// Fuseau de conversion des dates UTC (QuickTime, mtime); None = fuseau du système
+    pub timezone: Option<Tz>,
+    /// Filtre des dates invraisemblables
+    pub plausible: &'a PlausibleDates,
+    /// Motifs de noms de fichiers datés
+    pub filename_dates: &'a FilenameDates,
+    /// Traitement des fichiers sans date dans leurs métadonnées
+    pub on_missing_date: MissingDatePolicy,
+    /// Racine du dépôt (dates des noms de dossiers)
+    pub input: &'a Path,
+}
+
+/// Détermine la date/heure de capture d'un fichier média.
+///
+/// Utilise le tag de date le plus prioritaire pour la famille du fichier (photo, RAW,
+/// vidéo, audio), puis un motif de nom de fichier daté (WhatsApp, Android,
+/// `--date-pattern`…). Les dates invraisemblables (horloge non réglée, hors de
+/// `--min-date`/`--max-date`, futur) sont ignorées au profit du candidat suivant.
+/// En l'absence de date, applique `--on-missing-date`: date de modification, date
+/// dans le nom du fichier ou d'un dossier, ou pas de date.
 ///
 /// # Arguments
 /// * `path` - Chemin vers le fichier média
+/// * `meta` - Métadonnées lues (None si aucun backend n'a rien renvoyé)
+/// * `policy` - Règles de datation
 ///
 /// # Returns
-/// La date/heure extraite des métadonnées ou de mtime
+/// La date/heure locale de la prise de vue, avec son décalage UTC s'il est connu,
+/// ou None si le fichier reste sans date
 ///
 /// # Errors
-/// Retourne une erreur si exiftool échoue ET que mtime n'est pas accessible
-pub fn extract_datetime_with_exiftool(path: &Path) -> Result<NaiveDateTime> {
-    match try_exiftool(path) {
-        Ok(dt) => Ok(dt),
-        Err(e) => {
-            log::warn!(
-                "exiftool failed for {}, using file mtime: {}",
+/// Retourne une erreur si mtime doit être utilisée mais n'est pas accessible
+pub fn resolve_capture_time(
+    path: &Path,
+    meta: Option<&MediaMetadata>,
+    policy: &DatePolicy,
+) -> Result<Option<CaptureTime>> {
+    let (tz, plausible) = (policy.timezone, policy.plausible);
+    let kind = MediaKind::from_path(path);
+    // Tags écartés avant le premier tag plausible
+    for d in meta.into_iter().flat_map(|m| m.date_candidates(kind)) {
+        let Some(reason) = plausible.reject_reason(d.value) else {
+            break;
+        };
+        log::info!(
+            "[IMPLAUSIBLE] {}: ignoring {} = {} ({})",
+            path.display(),
+            d.tag,
+            d.raw,
+            reason
+        );
+    }
+
+    let capture = meta.and_then(|m| {
+        m.capture_date(kind, plausible)
+            .zip(m.capture_time(kind, tz, plausible))
+    });
+    match capture {
+        Some((d, capture)) => {
+            log::debug!(
+                "{}: date from {} ({}) -> {}",
                 path.display(),
-                e
+                capture.source,
+                d.raw,
+                capture.to_iso8601()
+            );
+            Ok(Some(capture))
+        }
+        None => match policy.filename_dates.find(path, plausible) {
+            Some((local, pattern)) => {
+                log::debug!(
+                    "{}: date from file name ({}) -> {}",
+                    path.display(),
+                    pattern,
+                    local
+                );
+                Ok(Some(filename_capture(local, pattern)))
+            }
+            None => missing_date(path, policy),
+        },
+    }
+}
+
+/// Date lue dans le nom du fichier (heure locale, sans décalage connu)
+fn filename_capture(local: NaiveDateTime, pattern: &str) -> CaptureTime {
+    CaptureTime {
+        local,
+        offset: None,
+        source: format!("filename:{}", pattern),
+        origin: DateOrigin::Filename,
+        sub_sec: None,
+        original: None,
+    }
+}
+
+/// Date d'un fichier sans date dans ses métadonnées, selon `--on-missing-date`
+fn missing_date(path: &Path, policy: &DatePolicy) -> Result<Option<CaptureTime>> {
+    let capture = match policy.on_missing_date {
+        MissingDatePolicy::Mtime => {
+            log::warn!(
+                "No date found via EXIF/metadata tags for {}, using file mtime",
+                path.display()
+            );
+            return extract_datetime_from_mtime(path, policy.timezone).map(Some);
+        }
+        MissingDatePolicy::Filename => match date_from_filename(path, policy.plausible) {
+            Some(dt) => filename_capture(dt, "generic"),
+            None => return Ok(None),
+        },
+        MissingDatePolicy::Folder => match date_from_folder(path, policy.input, policy.plausible) {
+            // Heure locale, sans décalage connu
+            Some(dt) => CaptureTime {
+                local: dt,
+                offset: None,
+                source: "folder".to_string(),
+                origin: DateOrigin::Folder,
+                sub_sec: None,
+                original: None,
+            },
+            None => return Ok(None),
+        },
+        MissingDatePolicy::Skip | MissingDatePolicy::Quarantine | MissingDatePolicy::UnknownDir => {
+            return Ok(None)
+        }
+    };
+
+    log::warn!(
+        "No date found via EXIF/metadata tags for {}, using {} date {}",
+        path.display(),
+        capture.source,
+        capture.local
+    );
+    Ok(Some(capture))
+}
+
+/// Interroge exiftool pour un lot de fichiers et parse la sortie JSON
+fn try_exiftool_batch(
+    exiftool: &ExiftoolPool,
+    paths: &[PathBuf],
+) -> Result<HashMap<PathBuf, MediaMetadata>> {
+    // -json -G1 : un objet par fichier, tags préfixés par leur groupe
+    // -n : valeurs brutes (dates non reformatées, GPS en degrés décimaux non signés
+    //      dans le groupe GPS, d'où les tags `…Ref` de l'hémisphère)
+    // Pas de QuickTimeUTC: les dates QuickTime restent en UTC et sont converties
+    // par `capture_time` (fuseau --timezone plutôt que celui de la machine)
+    // -time:all : tous les tags de date
+    let mut args: Vec<&OsStr> = [
+        "-json",
+        "-G1",
+        "-n",
+        "-time:all",
+        "-Make",
+        "-Model",
+        "-GPSLatitude",
+        "-GPSLongitude",
+        "-GPSAltitude",
+        "-GPSLatitudeRef",
+        "-GPSLongitudeRef",
+        "-GPSAltitudeRef",
+        "-SubSecTimeOriginal",
+        "-SubSecTime",
+        "-SubSecTimeDigitized",
+        "-OffsetTimeOriginal",
+        "-OffsetTime",
+        "-OffsetTimeDigitized",
+        "-ContentIdentifier",
+        "-MediaGroupUUID",
+    ]
+    .iter()
+    .map(OsStr::new)
+    .collect();
+    let options = args.len();
+    for path in paths {
+        if has_line_break(path.as_os_str()) {
+            log::warn!(
+                "Skipping {:?} for exiftool: line breaks in file names are not supported",
+                path
             );
-            extract_datetime_from_mtime(path)
+        } else {
+            args.push(path.as_os_str());
+        }
+    }
+    if args.len() == options {
+        return Ok(HashMap::new());
+    }
+
+    let stdout = exiftool.execute(&args).context("exiftool failed to run")?;
+
+    // Aucun fichier lisible: exiftool n'écrit rien sur stdout.
+    if stdout.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // exiftool renvoie SourceFile tel qu'on lui a transmis le chemin.
+    let by_name: HashMap<String, &PathBuf> = paths
+        .iter()
+        .map(|p| (p.to_string_lossy().into_owned(), p))
+        .collect();
+
+    let mut out = HashMap::with_capacity(paths.len());
+    for (source, meta) in parse_exiftool_json(&stdout)? {
+        if let Some(path) = by_name.get(&source) {
+            out.insert((*path).clone(), meta);
         }
     }
+    Ok(out)
 }
 
-/// Tente d'extraire la date via exiftool
-fn try_exiftool(path: &Path) -> Result<NaiveDateTime> {
-    // On demande plusieurs tags dans l'ordre, et on prend le premier non-vide.
-    // -s -s -s : sortie brute sans label
-    // -d : format homogène pour parser
-    // Tags choisis pour couvrir photos + vidéos (QuickTime/MP4)
-    let tags = [
-        "-DateTimeOriginal",
-        "-CreateDate",
-        "-MediaCreateDate",
-        "-TrackCreateDate",
-        "-ModifyDate",
-    ];
+/// Parse une sortie JSON exiftool (`-json -G1 -n`)
+///
+/// # Returns
+/// Les couples (SourceFile, métadonnées) dans l'ordre de la sortie
+///
+/// # Errors
+/// Retourne une erreur si le JSON est invalide ou n'est pas un tableau
+pub fn parse_exiftool_json(json: &str) -> Result<Vec<(String, MediaMetadata)>> {
+    let json: Value = serde_json::from_str(json).context("invalid exiftool JSON output")?;
+    let entries = match json {
+        Value::Array(a) => a,
+        _ => bail!("unexpected exiftool JSON output (not an array)"),
+    };
 
-    let mut cmd = Command::new("exiftool");
-    cmd.arg("-s")
-        .arg("-s")
-        .arg("-s")
-        .arg("-api")
-        .arg("QuickTimeUTC=1")
-        .arg("-d")
-        .arg("%Y-%m-%d %H:%M:%S");
+    let mut out = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let obj = match entry.as_object() {
+            Some(o) => o,
+            None => continue,
+        };
+        let source = match obj.get("SourceFile").and_then(Value::as_str) {
+            Some(s) => s,
+            None => continue,
+        };
+        out.push((source.to_string(), MediaMetadata::from_json(obj)));
+    }
+    Ok(out)
+}
 
-    for t in tags {
-        cmd.arg(t);
+/// Convertit une valeur JSON scalaire en texte (exiftool écrit les valeurs numériques sans guillemets)
+fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => {
+            let s = s.trim();
+            (!s.is_empty()).then(|| s.to_string())
+        }
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
     }
-    cmd.arg(path);
+}
 
-    let out = cmd
-        .output()
-        .with_context(|| format!("exiftool failed to run on {}", path.display()))?;
+/// Parse une date au format EXIF (`YYYY:MM:DD HH:MM:SS[.sss][±HH:MM|Z]`).
+///
+/// # Returns
+/// La date telle qu'écrite (fractions de seconde comprises), le décalage UTC éventuel
+/// et la fraction de seconde si la valeur en contient une
+fn parse_exif_datetime(s: &str) -> Option<(NaiveDateTime, Option<FixedOffset>, Option<u32>)> {
+    let head = s.get(..19)?;
+    let dt = NaiveDateTime::parse_from_str(head, "%Y:%m:%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(head, "%Y-%m-%d %H:%M:%S"))
+        .ok()?;
 
-    if !out.status.success() {
-        let stderr = String::from_utf8_lossy(&out.stderr);
-        bail!("exiftool error: {}", stderr.trim());
+    let mut tail = &s[19..];
+    let mut value = dt;
+    let mut sub_sec = None;
+    if let Some(frac) = tail.strip_prefix('.') {
+        let digits = frac.len() - frac.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if let Some(n) = parse_sub_seconds(&frac[..digits]) {
+            value = dt.with_nanosecond(n).unwrap_or(dt);
+            sub_sec = Some(n);
+        }
+        tail = &frac[digits..];
+    }
+
+    Some((value, parse_utc_offset(tail), sub_sec))
+}
+
+/// Parse un décalage UTC (`+02:00`, `-0530`, `Z`)
+fn parse_utc_offset(s: &str) -> Option<FixedOffset> {
+    let s = s.trim();
+    if s == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match s.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = s[1..].chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
     }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
 
-    // exiftool renvoie une ligne par tag demandé (souvent vide si absent).
-    // On cherche la première ligne qui ressemble à une date formatée.
-    let stdout = String::from_utf8_lossy(&out.stdout);
-    for line in stdout.lines() {
-        let s = line.trim();
-        if s.is_empty() {
-            continue;
+/// Convertit une date UTC en heure locale du fuseau `tz` (None = fuseau du système)
+///
+/// # Returns
+/// L'heure locale et son décalage UTC
+fn utc_to_local(utc: NaiveDateTime, tz: Option<Tz>) -> (NaiveDateTime, FixedOffset) {
+    match tz {
+        Some(tz) => {
+            let dt = tz.from_utc_datetime(&utc);
+            (dt.naive_local(), dt.offset().fix())
         }
-        if let Ok(dt) = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S") {
-            return Ok(dt);
+        None => {
+            let dt = Local.from_utc_datetime(&utc);
+            (dt.naive_local(), *dt.offset())
         }
     }
+}
 
-    bail!(
-        "No date found via EXIF/metadata tags for {}",
-        path.display()
-    );
+/// Convertit une fraction de seconde EXIF (chiffres après la virgule, ex: `"045"`) en nanosecondes
+fn parse_sub_seconds(s: &str) -> Option<u32> {
+    let digits: String = s
+        .trim()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .take(9)
+        .collect();
+    if digits.is_empty() {
+        return None;
+    }
+    format!("{:0<9}", digits).parse().ok()
 }
 
-/// Extrait la date de modification du fichier comme fallback
-fn extract_datetime_from_mtime(path: &Path) -> Result<NaiveDateTime> {
+/// Extrait la date de modification du fichier comme fallback, en heure locale du fuseau `tz`
+fn extract_datetime_from_mtime(path: &Path, tz: Option<Tz>) -> Result<CaptureTime> {
     let metadata = std::fs::metadata(path)
         .with_context(|| format!("Cannot read metadata for {}", path.display()))?;
 
@@ -109,10 +694,20 @@ fn extract_datetime_from_mtime(path: &Path) -> Result<NaiveDateTime> {
         .modified()
         .with_context(|| format!("Cannot get modification time for {}", path.display()))?;
 
-    datetime_from_systemtime(mtime)
+    // Précision à la seconde: mtime ne date pas la prise de vue
+    let utc = datetime_from_systemtime(mtime)?;
+    let (local, offset) = utc_to_local(utc.with_nanosecond(0).unwrap_or(utc), tz);
+    Ok(CaptureTime {
+        local,
+        offset: Some(offset),
+        source: "mtime".to_string(),
+        origin: DateOrigin::Mtime,
+        sub_sec: None,
+        original: None,
+    })
 }
 
-/// Convertit SystemTime en NaiveDateTime
+/// Convertit SystemTime en NaiveDateTime (UTC)
 fn datetime_from_systemtime(time: SystemTime) -> Result<NaiveDateTime> {
     let duration = time
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -121,9 +716,222 @@ fn datetime_from_systemtime(time: SystemTime) -> Result<NaiveDateTime> {
     let secs = duration.as_secs() as i64;
     let nsecs = duration.subsec_nanos();
 
-    use chrono::DateTime;
     let dt = DateTime::from_timestamp(secs, nsecs)
         .ok_or_else(|| anyhow::anyhow!("Invalid timestamp"))?;
 
     Ok(dt.naive_utc())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gps(json: Value) -> Option<GpsPosition> {
+        MediaMetadata::from_json(json.as_object().unwrap()).gps
+    }
+
+    #[test]
+    fn gps_applies_hemisphere_refs() {
+        let pos = gps(serde_json::json!({
+            "GPS:GPSLatitude": 33.86,
+            "GPS:GPSLatitudeRef": "S",
+            "GPS:GPSLongitude": 151.21,
+            "GPS:GPSLongitudeRef": "E",
+            "GPS:GPSAltitude": 12.5,
+            "GPS:GPSAltitudeRef": 1,
+        }))
+        .unwrap();
+        assert_eq!(pos.latitude, -33.86);
+        assert_eq!(pos.longitude, 151.21);
+        assert_eq!(pos.altitude, Some(-12.5));
+    }
+
+    #[test]
+    fn gps_prefers_composite_values() {
+        // `Composite` est lu avant `GPS` (ordre alphabétique), `XMP-exif` après
+        let pos = gps(serde_json::json!({
+            "Composite:GPSLatitude": -33.86,
+            "Composite:GPSLongitude": -70.65,
+            "GPS:GPSLatitude": 33.86,
+            "GPS:GPSLongitude": 70.65,
+            "XMP-exif:GPSLatitude": 33.86,
+            "XMP-exif:GPSLongitude": 70.65,
+        }))
+        .unwrap();
+        assert_eq!((pos.latitude, pos.longitude), (-33.86, -70.65));
+        assert_eq!(pos.altitude, None);
+    }
+
+    #[test]
+    fn gps_requires_both_coordinates() {
+        assert!(gps(serde_json::json!({ "GPS:GPSLatitude": 48.85 })).is_none());
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    fn hours(h: f64) -> Option<FixedOffset> {
+        FixedOffset::east_opt((h * 3600.0) as i32)
+    }
+
+    fn capture(json: Value, kind: MediaKind, tz: Option<Tz>) -> CaptureTime {
+        let plausible = PlausibleDates::new(None, None).unwrap();
+        MediaMetadata::from_json(json.as_object().unwrap())
+            .capture_time(kind, tz, &plausible)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_utc_offsets() {
+        let cases = [
+            ("+02:00", hours(2.0)),
+            ("-05:30", hours(-5.5)),
+            ("+0100", hours(1.0)),
+            (" +09:00 ", hours(9.0)),
+            ("Z", hours(0.0)),
+            ("", None),
+            ("02:00", None),
+            ("+2:00", None),
+            ("+02:0x", None),
+            ("+02:00:00", None),
+            ("UTC", None),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_utc_offset(raw), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn converts_utc_across_dst() {
+        let paris: Tz = "Europe/Paris".parse().unwrap();
+        let cases = [
+            // Passage à l'heure d'été (31 mars 2024, 02:00 -> 03:00)
+            ("2024-03-31 00:30:00", "2024-03-31 01:30:00", 1.0),
+            ("2024-03-31 01:30:00", "2024-03-31 03:30:00", 2.0),
+            // Retour à l'heure d'hiver (27 octobre 2024): 02:30 locale deux fois
+            ("2024-10-27 00:30:00", "2024-10-27 02:30:00", 2.0),
+            ("2024-10-27 01:30:00", "2024-10-27 02:30:00", 1.0),
+        ];
+        for (utc, local, offset) in cases {
+            assert_eq!(
+                utc_to_local(at(utc), Some(paris)),
+                (at(local), hours(offset).unwrap()),
+                "{}",
+                utc
+            );
+        }
+    }
+
+    #[test]
+    fn exif_date_takes_its_own_sub_seconds_and_offset() {
+        let both = serde_json::json!({
+            "ExifIFD:DateTimeOriginal": "2024:05:01 10:00:00",
+            "ExifIFD:CreateDate": "2024:05:01 10:00:01",
+            "IFD0:ModifyDate": "2024:06:01 12:00:00",
+            "ExifIFD:SubSecTimeOriginal": "045",
+            "ExifIFD:SubSecTimeDigitized": "5",
+            "ExifIFD:SubSecTime": "999",
+            "ExifIFD:OffsetTimeOriginal": "+02:00",
+            "ExifIFD:OffsetTimeDigitized": "+03:00",
+            "ExifIFD:OffsetTime": "-04:00",
+        });
+        let c = capture(both.clone(), MediaKind::Photo, None);
+        assert_eq!(c.source, "ExifIFD:DateTimeOriginal");
+        assert_eq!(c.local, at("2024-05-01 10:00:00.045"));
+        assert_eq!(c.sub_sec, Some(45_000_000));
+        assert_eq!(c.offset, hours(2.0));
+
+        // Sans DateTimeOriginal: CreateDate et les tags `…Digitized`
+        let mut digitized = both;
+        digitized
+            .as_object_mut()
+            .unwrap()
+            .remove("ExifIFD:DateTimeOriginal");
+        let c = capture(digitized, MediaKind::Photo, None);
+        assert_eq!(c.source, "ExifIFD:CreateDate");
+        assert_eq!(c.local, at("2024-05-01 10:00:01.5"));
+        assert_eq!(c.offset, hours(3.0));
+    }
+
+    #[test]
+    fn offset_and_fraction_in_the_value_win() {
+        let c = capture(
+            serde_json::json!({
+                "ExifIFD:DateTimeOriginal": "2024:05:01 10:00:00.12+01:00",
+                "ExifIFD:SubSecTimeOriginal": "999",
+                "ExifIFD:OffsetTimeOriginal": "+05:00",
+            }),
+            MediaKind::Photo,
+            None,
+        );
+        assert_eq!(c.local, at("2024-05-01 10:00:00.12"));
+        assert_eq!(c.offset, hours(1.0));
+    }
+
+    #[test]
+    fn quicktime_dates_are_converted_from_utc() {
+        let paris: Tz = "Europe/Paris".parse().unwrap();
+        let c = capture(
+            serde_json::json!({ "QuickTime:CreateDate": "2024:07:01 08:00:00" }),
+            MediaKind::Video,
+            Some(paris),
+        );
+        assert_eq!(c.local, at("2024-07-01 10:00:00"));
+        assert_eq!(c.offset, hours(2.0));
+
+        // La date locale de l'iPhone, avec son décalage, n'est pas convertie
+        let c = capture(
+            serde_json::json!({
+                "QuickTime:CreateDate": "2024:07:01 08:00:00",
+                "Keys:CreationDate": "2024:07:01 17:00:00+09:00",
+            }),
+            MediaKind::Video,
+            Some(paris),
+        );
+        assert_eq!(c.source, "Keys:CreationDate");
+        assert_eq!(c.local, at("2024-07-01 17:00:00"));
+        assert_eq!(c.offset, hours(9.0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_batch_is_retried_one_file_at_a_time() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::time::Duration;
+
+        // Faux exiftool: bloque sur `bad.jpg`, sinon un objet JSON par fichier
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("fake-exiftool");
+        std::fs::write(
+            &script,
+            r#"#!/bin/sh
+files=""
+while IFS= read -r line; do
+  case "$line" in
+    -execute*)
+      case "$files" in *bad.jpg*) exec sleep 30;; esac
+      out=""
+      for f in $files; do out="$out{\"SourceFile\":\"$f\",\"IFD0:Make\":\"Canon\"},"; done
+      echo "[${out%,}]"; echo "{ready${line#-execute}}"; files="";;
+    False) exit 0;;
+    -*) ;;
+    *) files="$files $line";;
+  esac
+done
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let pool = ExiftoolPool::with_program(1, script, Duration::from_millis(300));
+
+        let paths: Vec<PathBuf> = ["a.jpg", "bad.jpg", "b.jpg"]
+            .iter()
+            .map(|n| dir.path().join(n))
+            .collect();
+        let metas = extract_metadata_batch_with_exiftool(&pool, &paths);
+        assert_eq!(metas.len(), 2);
+        assert_eq!(metas[&paths[0]].make.as_deref(), Some("Canon"));
+        assert_eq!(metas[&paths[2]].make.as_deref(), Some("Canon"));
+    }
+}
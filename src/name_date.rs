@@ -0,0 +1,317 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use regex::{Captures, Regex};
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::plausible::PlausibleDates;
+
+/// Noms de fichiers connus qui contiennent la date de prise de vue (ou de réception),
+/// essayés dans l'ordre. Groupes nommés: `y`, `m`, `d`, et `H`, `M`, `S` optionnels.
+const KNOWN_PATTERNS: &[(&str, &str)] = &[
+    // IMG-20250723-WA0001.jpg, VID-20250723-WA0002.mp4 (date de réception, sans heure)
+    (
+        "whatsapp",
+        r"(?i)^(?:IMG|VID|AUD|PTT|STK|DOC)-(?P<y>\d{4})(?P<m>\d{2})(?P<d>\d{2})-WA\d+",
+    ),
+    // Screenshot_20250723-085404, Screenshot 2025-07-23 at 08.54.04 (macOS, en anglais
+    // ou en français)
+    (
+        "screenshot",
+        r"(?i)^(?:Screenshot|Screen Shot|Capture d.écran)[_ -](?P<y>\d{4})-?(?P<m>\d{2})-?(?P<d>\d{2})[-_ ](?:at |à )?(?P<H>\d{2})[.\-:]?(?P<M>\d{2})[.\-:]?(?P<S>\d{2})",
+    ),
+    // IMG_20250723_085404.jpg, VID_20250723_085404.mp4, PXL_20250723_085404123.jpg
+    (
+        "android",
+        r"(?i)^(?:IMG|VID|PANO|MVIMG|BURST|PXL)_(?P<y>\d{4})(?P<m>\d{2})(?P<d>\d{2})_(?P<H>\d{2})(?P<M>\d{2})(?P<S>\d{2})",
+    ),
+    // signal-2025-07-23-085404.jpg, signal-2025-07-23-08-54-04-123.jpg
+    (
+        "signal",
+        r"(?i)^signal-(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2})-(?P<H>\d{2})-?(?P<M>\d{2})-?(?P<S>\d{2})",
+    ),
+    // photo_2025-07-23_08-54-04.jpg, video_2025-07-23_08-54-04.mp4 (Telegram Desktop)
+    (
+        "telegram",
+        r"(?i)^(?:photo|video|file)_(?P<y>\d{4})-(?P<m>\d{2})-(?P<d>\d{2})_(?P<H>\d{2})-(?P<M>\d{2})-(?P<S>\d{2})",
+    ),
+    // Scan_20250723_085404.jpg, scan 2025-07-23.tif
+    (
+        "scanner",
+        r"(?i)^scan(?:ned)?[_ -]?(?P<y>\d{4})-?(?P<m>\d{2})-?(?P<d>\d{2})(?:[_ -]?(?P<H>\d{2})(?P<M>\d{2})(?P<S>\d{2}))?",
+    ),
+];
+
+/// Motif de nom de fichier daté
+#[derive(Debug)]
+struct DatePattern {
+    name: String,
+    regex: Regex,
+}
+
+/// Bibliothèque de motifs de noms de fichiers datés: motifs de l'utilisateur
+/// (`--date-pattern`), puis motifs connus (WhatsApp, Android, captures d'écran…)
+#[derive(Debug)]
+pub struct FilenameDates {
+    patterns: Vec<DatePattern>,
+}
+
+impl FilenameDates {
+    /// Compile les motifs de l'utilisateur et ajoute les motifs connus.
+    ///
+    /// # Arguments
+    /// * `custom` - Expressions régulières (groupes nommés `y`, `m`, `d`, et `H`,
+    ///   `M`, `S` optionnels), nommées `custom-1`, `custom-2`… dans le rapport
+    ///
+    /// # Errors
+    /// Retourne une erreur si un motif est invalide ou n'a pas les groupes `y`, `m`, `d`
+    pub fn new(custom: &[String]) -> Result<Self> {
+        let mut patterns = Vec::with_capacity(custom.len() + KNOWN_PATTERNS.len());
+        for (i, source) in custom.iter().enumerate() {
+            let regex = Regex::new(source)
+                .map_err(|e| anyhow!("Invalid --date-pattern {:?}: {}", source, e))?;
+            let names: Vec<&str> = regex.capture_names().flatten().collect();
+            if let Some(missing) = ["y", "m", "d"].iter().find(|g| !names.contains(g)) {
+                bail!(
+                    "Invalid --date-pattern {:?}: missing named group (?P<{}>...)",
+                    source,
+                    missing
+                );
+            }
+            patterns.push(DatePattern {
+                name: format!("custom-{}", i + 1),
+                regex,
+            });
+        }
+        for (name, source) in KNOWN_PATTERNS {
+            patterns.push(DatePattern {
+                name: name.to_string(),
+                regex: Regex::new(source).expect("valid built-in date pattern"),
+            });
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Cherche la date d'un fichier dans son nom (sans extension), motif par motif.
+    /// Une date invraisemblable est ignorée au profit du motif suivant.
+    ///
+    /// # Returns
+    /// La date (minuit si le nom ne contient pas l'heure) et le nom du motif reconnu
+    pub fn find(&self, path: &Path, plausible: &PlausibleDates) -> Option<(NaiveDateTime, &str)> {
+        let stem = path.file_stem()?.to_str()?;
+        self.patterns.iter().find_map(|p| {
+            let dt = datetime_from_captures(&p.regex.captures(stem)?)?;
+            plausible.accepts(dt).then_some((dt, p.name.as_str()))
+        })
+    }
+}
+
+/// Date quelconque dans un nom de fichier (`--on-missing-date filename`): `20250723`,
+/// `2025-07-23`, suivie ou non de l'heure (`_085404`, ` 08.54.04`, `T08:54:04`)
+static FILENAME_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        (?:^|\D)
+        (?P<y>(?:19|20)\d{2}) [-_.]? (?P<m>0[1-9]|1[0-2]) [-_.]? (?P<d>0[1-9]|[12]\d|3[01])
+        (?: [-_.\ T]? (?P<H>[01]\d|2[0-3]) [-_.:h]? (?P<M>[0-5]\d) [-_.:m]? (?P<S>[0-5]\d) )?
+        (?:\D|$)",
+    )
+    .expect("valid filename date regex")
+});
+
+/// Date dans un nom de dossier: `2019`, `2019-07`, `2019-07-14`, `2019_07 Vacances`
+static FOLDER_DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?x)
+        (?:^|\D)
+        (?P<y>(?:19|20)\d{2})
+        (?: [-_.\ ]? (?P<m>0[1-9]|1[0-2]) (?: [-_.\ ]? (?P<d>0[1-9]|[12]\d|3[01]) )? )?
+        (?:\D|$)",
+    )
+    .expect("valid folder date regex")
+});
+
+/// Cherche une date n'importe où dans le nom d'un fichier (sans son extension).
+///
+/// Moins sûr que les motifs connus (un nombre du nom peut ressembler à une date):
+/// seulement avec `--on-missing-date filename`.
+///
+/// # Returns
+/// La première date plausible (minuit si le nom ne contient pas l'heure), None si le
+/// nom n'en contient pas
+pub fn date_from_filename(path: &Path, plausible: &PlausibleDates) -> Option<NaiveDateTime> {
+    let stem = path.file_stem()?.to_str()?;
+    FILENAME_DATE
+        .captures_iter(stem)
+        .filter_map(|c| datetime_from_captures(&c))
+        .find(|dt| plausible.accepts(*dt))
+}
+
+/// Cherche une date dans le nom des dossiers d'un fichier, du plus proche au plus
+/// éloigné, sans remonter au-delà du dépôt.
+///
+/// Une date incomplète est ramenée au début de la période (`2019-07` -> 1er juillet 2019).
+///
+/// # Arguments
+/// * `path` - Fichier du dépôt
+/// * `input` - Racine du dépôt (son propre nom n'est pas utilisé)
+/// * `plausible` - Intervalle des dates acceptées
+pub fn date_from_folder(
+    path: &Path,
+    input: &Path,
+    plausible: &PlausibleDates,
+) -> Option<NaiveDateTime> {
+    let rel = path.parent()?.strip_prefix(input).ok()?;
+    rel.ancestors()
+        .filter_map(|dir| dir.file_name()?.to_str())
+        .find_map(|name| {
+            FOLDER_DATE
+                .captures_iter(name)
+                .filter_map(|c| datetime_from_captures(&c))
+                .find(|dt| plausible.in_range(*dt))
+        })
+}
+
+/// Construit une date à partir des groupes `y`, `m`, `d`, `H`, `M`, `S` (optionnels
+/// sauf l'année); None si elle n'existe pas (ex: 31 avril)
+fn datetime_from_captures(c: &Captures) -> Option<NaiveDateTime> {
+    let num = |name: &str, default: u32| {
+        c.name(name)
+            .map_or(Some(default), |m| m.as_str().parse::<u32>().ok())
+    };
+    let year = c.name("y")?.as_str().parse().ok()?;
+    let date = NaiveDate::from_ymd_opt(year, num("m", 1)?, num("d", 1)?)?;
+    let time = NaiveTime::from_hms_opt(num("H", 0)?, num("M", 0)?, num("S", 0)?)?;
+    Some(date.and_time(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn find(dates: &FilenameDates, name: &str) -> Option<(NaiveDateTime, String)> {
+        dates
+            .find(Path::new(name), &PlausibleDates::new(None, None).unwrap())
+            .map(|(d, p)| (d, p.to_string()))
+    }
+
+    #[test]
+    fn finds_built_in_patterns() {
+        let dates = FilenameDates::new(&[]).unwrap();
+        let cases = [
+            ("IMG-20250723-WA0001.jpg", "whatsapp", "2025-07-23 00:00:00"),
+            ("VID-20250723-WA0002.mp4", "whatsapp", "2025-07-23 00:00:00"),
+            (
+                "Screenshot_20250723-085404.png",
+                "screenshot",
+                "2025-07-23 08:54:04",
+            ),
+            (
+                "Screenshot 2025-07-23 at 08.54.04.png",
+                "screenshot",
+                "2025-07-23 08:54:04",
+            ),
+            ("IMG_20250723_085404.jpg", "android", "2025-07-23 08:54:04"),
+            (
+                "PXL_20250723_085404123.jpg",
+                "android",
+                "2025-07-23 08:54:04",
+            ),
+            (
+                "signal-2025-07-23-085404.jpg",
+                "signal",
+                "2025-07-23 08:54:04",
+            ),
+            (
+                "photo_2025-07-23_08-54-04.jpg",
+                "telegram",
+                "2025-07-23 08:54:04",
+            ),
+            ("Scan_20250723_085404.jpg", "scanner", "2025-07-23 08:54:04"),
+            ("scan 2025-07-23.tif", "scanner", "2025-07-23 00:00:00"),
+        ];
+        for (name, pattern, expected) in cases {
+            assert_eq!(
+                find(&dates, name),
+                Some((dt(expected), pattern.to_string())),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_names_without_a_known_pattern() {
+        let dates = FilenameDates::new(&[]).unwrap();
+        assert_eq!(find(&dates, "DSC_0001.jpg"), None);
+        assert_eq!(find(&dates, "vacances 20180506.jpg"), None);
+        // 13e mois: pas une date
+        assert_eq!(find(&dates, "IMG-20251340-WA0001.jpg"), None);
+        // Horloge non réglée
+        assert_eq!(find(&dates, "IMG_20000101_000000.jpg"), None);
+    }
+
+    #[test]
+    fn custom_patterns_come_first() {
+        let dates = FilenameDates::new(&[r"^IMG_(?P<y>\d{4})(?P<m>\d{2})(?P<d>\d{2})".to_string()])
+            .unwrap();
+        assert_eq!(
+            find(&dates, "IMG_20250723_085404.jpg"),
+            Some((dt("2025-07-23 00:00:00"), "custom-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_custom_patterns() {
+        assert!(FilenameDates::new(&["(".to_string()]).is_err());
+        assert!(FilenameDates::new(&[r"(?P<y>\d{4})(?P<m>\d{2})".to_string()]).is_err());
+    }
+
+    #[test]
+    fn finds_generic_dates() {
+        let plausible = PlausibleDates::new(None, None).unwrap();
+        assert_eq!(
+            date_from_filename(Path::new("vacances 20180506.jpg"), &plausible),
+            Some(dt("2018-05-06 00:00:00"))
+        );
+        assert_eq!(
+            date_from_filename(Path::new("2018-05-06T10:11:12.jpg"), &plausible),
+            Some(dt("2018-05-06 10:11:12"))
+        );
+        assert_eq!(
+            date_from_filename(Path::new("123456.jpg"), &plausible),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_folder_dates() {
+        let plausible = PlausibleDates::new(None, None).unwrap();
+        let input = Path::new("/depot");
+        assert_eq!(
+            date_from_folder(
+                Path::new("/depot/2019-07 Vacances/a.jpg"),
+                input,
+                &plausible
+            ),
+            Some(dt("2019-07-01 00:00:00"))
+        );
+        // Le dossier le plus proche l'emporte; `2000` n'est pas une date sentinelle ici
+        assert_eq!(
+            date_from_folder(Path::new("/depot/2019/2000/a.jpg"), input, &plausible),
+            Some(dt("2000-01-01 00:00:00"))
+        );
+        assert_eq!(
+            date_from_folder(
+                Path::new("/2019/depot/a.jpg"),
+                Path::new("/2019/depot"),
+                &plausible
+            ),
+            None
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use chrono::{Datelike, NaiveDateTime, Timelike};
 use std::path::{Component, Path, PathBuf};
 
+use crate::template::{NamingContext, Template};
+
 /// Infère le tag à partir du chemin relatif du fichier par rapport à input_root.
 ///
 /// Si le fichier est directement sous input_root, retourne None.
@@ -29,71 +30,58 @@ pub fn infer_tag(input_root: &Path, src: &Path) -> Option<String> {
 
 /// Construit le chemin du répertoire de destination.
 ///
-/// Format: output_root/YYYY/MM ou output_root/YYYY/MM TAG
+/// Format: output_root/<layout>, par défaut output_root/YYYY/MM ou output_root/YYYY/MM TAG
 ///
 /// # Arguments
 /// * `output_root` - Racine du répertoire de sortie
-/// * `dt` - Date/heure du fichier
-/// * `tag` - Tag optionnel (nom du dossier source)
+/// * `layout` - Template des dossiers (`--layout`)
+/// * `ctx` - Informations du fichier (date, tag, appareil…)
 ///
 /// # Returns
 /// Le chemin complet du répertoire de destination
-pub fn build_target_dir(output_root: &Path, dt: &NaiveDateTime, tag: Option<&str>) -> PathBuf {
-    let year = format!("{:04}", dt.year());
-    let month = format!("{:02}", dt.month());
-
-    let month_dir_name = match tag {
-        Some(t) if !t.trim().is_empty() => format!("{} {}", month, t.trim()),
-        _ => month,
-    };
+pub fn build_target_dir(output_root: &Path, layout: &Template, ctx: &NamingContext) -> PathBuf {
+    let rendered = layout.render(ctx);
 
-    output_root.join(year).join(month_dir_name)
+    // Les composants vides (placeholder absent hors section optionnelle) sont ignorés
+    rendered
+        .split('/')
+        .map(str::trim)
+        .filter(|c| !c.is_empty() && *c != "." && *c != "..")
+        .fold(output_root.to_path_buf(), |dir, c| dir.join(c))
 }
 
-/// Formate le nom de fichier basé sur la date.
+/// Formate le nom de fichier.
 ///
-/// Format: YYYY-MM-DD HH.MM.SS.ext
+/// Format: <name>.ext, par défaut YYYY-MM-DD HH.MM.SS.ext
 ///
 /// # Arguments
-/// * `dt` - Date/heure du fichier
+/// * `name` - Template du nom (`--name`)
+/// * `ctx` - Informations du fichier (date, tag, appareil…)
 /// * `ext` - Extension du fichier (sans point)
 ///
 /// # Returns
 /// Le nom de fichier formaté
-pub fn format_filename(dt: &NaiveDateTime, ext: &str) -> String {
-    format!(
-        "{:04}-{:02}-{:02} {:02}.{:02}.{:02}.{}",
-        dt.year(),
-        dt.month(),
-        dt.day(),
-        dt.hour(),
-        dt.minute(),
-        dt.second(),
-        ext
-    )
+pub fn format_filename(name: &Template, ctx: &NamingContext, ext: &str) -> String {
+    format!("{}.{}", name.render(ctx), ext)
 }
 
 /// Formate le nom de fichier avec un suffixe (pour gérer les collisions).
 ///
-/// Format: YYYY-MM-DD HH.MM.SS SUFFIX.ext
+/// Format: <name> SUFFIX.ext
 ///
 /// # Arguments
-/// * `dt` - Date/heure du fichier
+/// * `name` - Template du nom (`--name`)
+/// * `ctx` - Informations du fichier (date, tag, appareil…)
 /// * `suffix` - Suffixe à ajouter (généralement un préfixe de hash)
 /// * `ext` - Extension du fichier (sans point)
 ///
 /// # Returns
 /// Le nom de fichier formaté avec suffixe
-pub fn format_filename_with_suffix(dt: &NaiveDateTime, suffix: &str, ext: &str) -> String {
-    format!(
-        "{:04}-{:02}-{:02} {:02}.{:02}.{:02} {}.{}",
-        dt.year(),
-        dt.month(),
-        dt.day(),
-        dt.hour(),
-        dt.minute(),
-        dt.second(),
-        suffix,
-        ext
-    )
+pub fn format_filename_with_suffix(
+    name: &Template,
+    ctx: &NamingContext,
+    suffix: &str,
+    ext: &str,
+) -> String {
+    format!("{} {}.{}", name.render(ctx), suffix, ext)
 }
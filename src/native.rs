@@ -0,0 +1,1082 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::metadata::{DateTag, GpsPosition, MediaMetadata};
+
+/// Nombre max d'entrées lues dans un IFD (protection contre les fichiers corrompus)
+const MAX_IFD_ENTRIES: u16 = 1024;
+/// Nombre max de boîtes lues dans un conteneur ISOBMFF/QuickTime
+const MAX_BOXES: usize = 4096;
+/// Taille max d'un bloc EXIF embarqué (HEIF)
+const MAX_EXIF_SIZE: u64 = 4 * 1024 * 1024;
+/// Taille max d'une MakerNote Apple lue
+const MAX_MAKER_NOTE_SIZE: u32 = 64 * 1024;
+/// En-tête des MakerNotes Apple (iPhone)
+const APPLE_MAKER_NOTE_HEADER: &[u8] = b"Apple iOS\0";
+/// Clé QuickTime de l'identifiant partagé par les deux moitiés d'une Live Photo
+const APPLE_CONTENT_ID_KEY: &[u8] = b"com.apple.quicktime.content.identifier";
+/// Clé QuickTime de la date de prise de vue des iPhone, en heure locale avec décalage
+const APPLE_CREATION_DATE_KEY: &[u8] = b"com.apple.quicktime.creationdate";
+/// UUID de la boîte `moov/uuid` des CR3 Canon, qui contient les blocs EXIF `CMT1`/`CMT2`
+const CANON_CR3_UUID: [u8; 16] = [
+    0x85, 0xC0, 0xB6, 0x87, 0x82, 0x0F, 0x11, 0xE0, 0x81, 0x11, 0xF4, 0xCE, 0x46, 0x2B, 0x6A, 0x48,
+];
+/// Début de l'en-tête des RAF Fujifilm
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-";
+/// Position, dans l'en-tête RAF, de l'offset de l'aperçu JPEG (qui porte l'EXIF)
+const RAF_JPEG_OFFSET_POS: u64 = 84;
+/// Secondes entre l'époque QuickTime (1904-01-01) et l'époque UNIX
+const QUICKTIME_EPOCH_OFFSET: i64 = 2_082_844_800;
+
+/// Marques `ftyp` des conteneurs HEIF/AVIF (images)
+const HEIF_BRANDS: &[&[u8; 4]] = &[
+    b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1",
+    b"avif",
+];
+
+/// Boîtes de premier niveau d'un fichier QuickTime sans `ftyp`
+const QUICKTIME_TOP_LEVEL: &[&[u8; 4]] = &[b"moov", b"mdat", b"wide", b"free", b"skip", b"pnot"];
+
+/// Formats reconnus par le lecteur natif
+enum Format {
+    Jpeg,
+    Tiff,
+    Heif,
+    QuickTime,
+    Raf,
+}
+
+/// Lit les métadonnées d'un fichier sans passer par exiftool.
+///
+/// Supporte l'EXIF des JPEG, TIFF (et RAW basés sur TIFF), HEIC/HEIF et des RAW
+/// CR3 (Canon) et RAF (Fujifilm), ainsi que les dates `mvhd`/`tkhd`/`mdhd` des MP4/MOV. L'identifiant des
+/// Live Photos est lu dans la MakerNote Apple et dans les clés QuickTime.
+///
+/// # Arguments
+/// * `path` - Chemin du fichier média
+///
+/// # Returns
+/// Les métadonnées lues, ou None si le format n'est pas reconnu
+///
+/// # Errors
+/// Retourne une erreur si le fichier ne peut pas être lu ou est mal formé
+pub fn read_metadata(path: &Path) -> Result<Option<MediaMetadata>> {
+    let mut f =
+        BufReader::new(File::open(path).with_context(|| format!("open {}", path.display()))?);
+
+    let mut magic = [0u8; 12];
+    let n = read_up_to(&mut f, &mut magic)?;
+    f.seek(SeekFrom::Start(0))?;
+
+    let meta = match sniff(&magic[..n]) {
+        Some(Format::Jpeg) => read_jpeg(&mut f, 0)?,
+        Some(Format::Tiff) => read_tiff(&mut f, 0)?,
+        Some(Format::Heif) => read_heif(&mut f)?,
+        Some(Format::QuickTime) => read_quicktime(&mut f)?,
+        Some(Format::Raf) => read_raf(&mut f)?,
+        None => return Ok(None),
+    };
+    Ok(Some(meta))
+}
+
+/// Identifie le format à partir des premiers octets du fichier
+fn sniff(magic: &[u8]) -> Option<Format> {
+    if magic.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(Format::Jpeg);
+    }
+    if magic.starts_with(RAF_MAGIC) {
+        return Some(Format::Raf);
+    }
+    // TIFF classique, plus les variantes ORF (IIRO/IIRS/MMOR) et RW2 (IIU\0)
+    if magic.len() >= 4 && (magic.starts_with(b"II") || magic.starts_with(b"MM")) {
+        let tag = &magic[2..4];
+        if [
+            &[0x2A, 0x00][..],
+            &[0x00, 0x2A],
+            b"RO",
+            b"RS",
+            b"OR",
+            b"U\0",
+        ]
+        .contains(&tag)
+        {
+            return Some(Format::Tiff);
+        }
+    }
+    if magic.len() >= 12 {
+        let box_type = &magic[4..8];
+        if box_type == b"ftyp" {
+            let brand = &magic[8..12];
+            if HEIF_BRANDS.iter().any(|b| &b[..] == brand) {
+                return Some(Format::Heif);
+            }
+            return Some(Format::QuickTime);
+        }
+        if QUICKTIME_TOP_LEVEL.iter().any(|b| &b[..] == box_type) {
+            return Some(Format::QuickTime);
+        }
+    }
+    None
+}
+
+// --- JPEG -------------------------------------------------------------------
+
+/// Parcourt les segments JPEG (commençant à `base`) jusqu'au segment APP1 `Exif`
+fn read_jpeg<R: Read + Seek>(r: &mut R, base: u64) -> Result<MediaMetadata> {
+    r.seek(SeekFrom::Start(base + 2))?;
+    loop {
+        let mut marker = [0u8; 2];
+        r.read_exact(&mut marker).context("truncated JPEG")?;
+        if marker[0] != 0xFF {
+            bail!("invalid JPEG marker");
+        }
+        // Octets de remplissage 0xFF
+        let mut m = marker[1];
+        while m == 0xFF {
+            let mut b = [0u8; 1];
+            r.read_exact(&mut b)?;
+            m = b[0];
+        }
+
+        match m {
+            // Début des données image / fin d'image: pas d'EXIF
+            0xDA | 0xD9 => return Ok(MediaMetadata::default()),
+            // Marqueurs sans longueur
+            0x01 | 0xD0..=0xD7 => continue,
+            _ => {}
+        }
+
+        let len = read_u16_be(r)? as i64;
+        if len < 2 {
+            bail!("invalid JPEG segment length");
+        }
+        let payload_len = (len - 2) as usize;
+
+        if m == 0xE1 && payload_len > 6 {
+            let mut payload = vec![0u8; payload_len];
+            r.read_exact(&mut payload)?;
+            if payload.starts_with(b"Exif\0\0") {
+                return read_tiff(&mut Cursor::new(&payload[6..]), 0);
+            }
+            continue;
+        }
+        r.seek(SeekFrom::Current(payload_len as i64))?;
+    }
+}
+
+// --- TIFF / EXIF ------------------------------------------------------------
+
+/// Entrée d'un IFD TIFF
+struct IfdEntry {
+    tag: u16,
+    typ: u16,
+    count: u32,
+    /// Valeur inline ou offset (4 octets bruts)
+    raw: [u8; 4],
+}
+
+/// Lecteur de structure TIFF (ordre des octets + offset de base)
+struct Tiff<'a, R> {
+    r: &'a mut R,
+    base: u64,
+    le: bool,
+}
+
+impl<R: Read + Seek> Tiff<'_, R> {
+    fn u16_from(&self, b: [u8; 2]) -> u16 {
+        if self.le {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        }
+    }
+
+    fn u32_from(&self, b: [u8; 4]) -> u32 {
+        if self.le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        }
+    }
+
+    fn read_at(&mut self, offset: u32, buf: &mut [u8]) -> Result<()> {
+        self.r.seek(SeekFrom::Start(self.base + offset as u64))?;
+        self.r.read_exact(buf).context("truncated TIFF data")?;
+        Ok(())
+    }
+
+    fn entries(&mut self, offset: u32) -> Result<Vec<IfdEntry>> {
+        let mut b2 = [0u8; 2];
+        self.read_at(offset, &mut b2)?;
+        let count = self.u16_from(b2).min(MAX_IFD_ENTRIES);
+
+        let mut buf = vec![0u8; count as usize * 12];
+        self.r.read_exact(&mut buf).context("truncated IFD")?;
+
+        Ok(buf
+            .chunks_exact(12)
+            .map(|c| IfdEntry {
+                tag: self.u16_from([c[0], c[1]]),
+                typ: self.u16_from([c[2], c[3]]),
+                count: self.u32_from([c[4], c[5], c[6], c[7]]),
+                raw: [c[8], c[9], c[10], c[11]],
+            })
+            .collect())
+    }
+
+    /// Valeur entière (SHORT ou LONG/IFD)
+    fn uint(&self, e: &IfdEntry) -> u32 {
+        match e.typ {
+            3 => self.u16_from([e.raw[0], e.raw[1]]) as u32,
+            _ => self.u32_from(e.raw),
+        }
+    }
+
+    /// Valeur ASCII (sans les NUL ni espaces de fin)
+    fn ascii(&mut self, e: &IfdEntry) -> Result<Option<String>> {
+        let len = e.count.min(256) as usize;
+        let bytes = if len <= 4 {
+            e.raw[..len].to_vec()
+        } else {
+            let mut buf = vec![0u8; len];
+            self.read_at(self.u32_from(e.raw), &mut buf)?;
+            buf
+        };
+        let s = String::from_utf8_lossy(&bytes);
+        let s = s.trim_end_matches('\0').trim();
+        Ok((!s.is_empty()).then(|| s.to_string()))
+    }
+
+    /// Valeurs RATIONAL (numérateur/dénominateur non signés)
+    fn rationals(&mut self, e: &IfdEntry) -> Result<Vec<f64>> {
+        if e.typ != 5 {
+            return Ok(Vec::new());
+        }
+        let n = e.count.min(8) as usize;
+        let mut buf = vec![0u8; n * 8];
+        self.read_at(self.u32_from(e.raw), &mut buf)?;
+        Ok(buf
+            .chunks_exact(8)
+            .map(|c| {
+                let num = self.u32_from([c[0], c[1], c[2], c[3]]) as f64;
+                let den = self.u32_from([c[4], c[5], c[6], c[7]]) as f64;
+                if den == 0.0 {
+                    0.0
+                } else {
+                    num / den
+                }
+            })
+            .collect())
+    }
+}
+
+/// Lit l'en-tête TIFF puis IFD0, l'IFD EXIF et l'IFD GPS
+fn read_tiff<R: Read + Seek>(r: &mut R, base: u64) -> Result<MediaMetadata> {
+    let (mut tiff, ifd0_offset) = open_tiff(r, base)?;
+
+    let mut meta = MediaMetadata::default();
+    let mut exif_offset = None;
+    let mut gps_offset = None;
+
+    for e in tiff.entries(ifd0_offset)? {
+        match e.tag {
+            0x010F => meta.make = tiff.ascii(&e)?,
+            0x0110 => meta.model = tiff.ascii(&e)?,
+            0x0132 => push_date(&mut meta, "IFD0:ModifyDate", tiff.ascii(&e)?),
+            0x8769 => exif_offset = Some(tiff.uint(&e)),
+            0x8825 => gps_offset = Some(tiff.uint(&e)),
+            _ => {}
+        }
+    }
+
+    if let Some(offset) = exif_offset {
+        read_exif_ifd(&mut tiff, offset, &mut meta)?;
+    }
+
+    if let Some(offset) = gps_offset {
+        meta.gps = read_gps(&mut tiff, offset)?;
+    }
+
+    Ok(meta)
+}
+
+/// Lit l'en-tête d'une structure TIFF; retourne le lecteur et l'offset du premier IFD
+fn open_tiff<R: Read + Seek>(r: &mut R, base: u64) -> Result<(Tiff<'_, R>, u32)> {
+    let mut header = [0u8; 8];
+    r.seek(SeekFrom::Start(base))?;
+    r.read_exact(&mut header).context("truncated TIFF header")?;
+
+    let le = match &header[..2] {
+        b"II" => true,
+        b"MM" => false,
+        _ => bail!("invalid TIFF byte order"),
+    };
+    let tiff = Tiff { r, base, le };
+    let ifd0_offset = tiff.u32_from([header[4], header[5], header[6], header[7]]);
+    Ok((tiff, ifd0_offset))
+}
+
+/// Lit les dates, fractions de seconde et décalages d'un IFD EXIF
+fn read_exif_ifd<R: Read + Seek>(
+    tiff: &mut Tiff<'_, R>,
+    offset: u32,
+    meta: &mut MediaMetadata,
+) -> Result<()> {
+    for e in tiff.entries(offset)? {
+        match e.tag {
+            0x9003 => push_date(meta, "ExifIFD:DateTimeOriginal", tiff.ascii(&e)?),
+            0x9004 => push_date(meta, "ExifIFD:CreateDate", tiff.ascii(&e)?),
+            0x9010 => meta.offset_time = tiff.ascii(&e)?,
+            0x9011 => meta.offset_time_original = tiff.ascii(&e)?,
+            0x9012 => meta.offset_time_digitized = tiff.ascii(&e)?,
+            0x9290 => meta.sub_sec_time = tiff.ascii(&e)?,
+            0x9291 => meta.sub_sec_time_original = tiff.ascii(&e)?,
+            0x9292 => meta.sub_sec_time_digitized = tiff.ascii(&e)?,
+            0x927C => meta.content_identifier = read_apple_maker_note(tiff, &e)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Lit l'identifiant de Live Photo (`ContentIdentifier`, tag 0x0011) d'une MakerNote Apple
+fn read_apple_maker_note<R: Read + Seek>(
+    tiff: &mut Tiff<'_, R>,
+    e: &IfdEntry,
+) -> Result<Option<String>> {
+    if e.count < 16 || e.count > MAX_MAKER_NOTE_SIZE {
+        return Ok(None);
+    }
+    let mut data = vec![0u8; e.count as usize];
+    tiff.read_at(tiff.u32_from(e.raw), &mut data)?;
+    if !data.starts_with(APPLE_MAKER_NOTE_HEADER) {
+        return Ok(None);
+    }
+
+    // En-tête de 14 octets ("Apple iOS\0", version, ordre des octets), puis un IFD
+    // dont les offsets sont relatifs au début de la MakerNote
+    let le = &data[12..14] == b"II";
+    let mut cursor = Cursor::new(&data);
+    let mut note = Tiff {
+        r: &mut cursor,
+        base: 0,
+        le,
+    };
+    for entry in note.entries(14)? {
+        if entry.tag == 0x0011 && entry.typ == 2 {
+            return note.ascii(&entry);
+        }
+    }
+    Ok(None)
+}
+
+/// Lit l'IFD GPS (latitude/longitude en degrés-minutes-secondes)
+fn read_gps<R: Read + Seek>(tiff: &mut Tiff<'_, R>, offset: u32) -> Result<Option<GpsPosition>> {
+    let mut lat = None;
+    let mut lon = None;
+    let mut alt = None;
+    let mut lat_ref = None;
+    let mut lon_ref = None;
+    let mut alt_below = false;
+
+    for e in tiff.entries(offset)? {
+        match e.tag {
+            0x0001 => lat_ref = tiff.ascii(&e)?,
+            0x0002 => lat = dms_to_degrees(&tiff.rationals(&e)?),
+            0x0003 => lon_ref = tiff.ascii(&e)?,
+            0x0004 => lon = dms_to_degrees(&tiff.rationals(&e)?),
+            0x0005 => alt_below = e.raw[0] == 1,
+            0x0006 => alt = tiff.rationals(&e)?.first().copied(),
+            _ => {}
+        }
+    }
+
+    let (Some(mut latitude), Some(mut longitude)) = (lat, lon) else {
+        return Ok(None);
+    };
+    if lat_ref.as_deref() == Some("S") {
+        latitude = -latitude;
+    }
+    if lon_ref.as_deref() == Some("W") {
+        longitude = -longitude;
+    }
+    let altitude = alt.map(|a| if alt_below { -a } else { a });
+
+    Ok(Some(GpsPosition {
+        latitude,
+        longitude,
+        altitude,
+    }))
+}
+
+/// Convertit [degrés, minutes, secondes] en degrés décimaux
+fn dms_to_degrees(v: &[f64]) -> Option<f64> {
+    match v {
+        [d, m, s, ..] => Some(d + m / 60.0 + s / 3600.0),
+        [d, m] => Some(d + m / 60.0),
+        [d] => Some(*d),
+        [] => None,
+    }
+}
+
+/// Ajoute un tag de date EXIF s'il est présent et valide
+fn push_date(meta: &mut MediaMetadata, tag: &str, raw: Option<String>) {
+    if let Some(d) = raw.and_then(|raw| DateTag::parse(tag, raw)) {
+        meta.dates.push(d);
+    }
+}
+
+// --- RAF (Fujifilm) ---------------------------------------------------------
+
+/// Lit l'EXIF de l'aperçu JPEG embarqué d'un RAF Fujifilm
+fn read_raf<R: Read + Seek>(r: &mut R) -> Result<MediaMetadata> {
+    r.seek(SeekFrom::Start(RAF_JPEG_OFFSET_POS))?;
+    let offset = read_u32_be(r).context("truncated RAF header")?;
+    read_jpeg(r, offset as u64)
+}
+
+// --- ISOBMFF (HEIF, MP4, MOV) ----------------------------------------------
+
+/// En-tête d'une boîte ISOBMFF/QuickTime
+struct BoxHeader {
+    typ: [u8; 4],
+    /// Début du contenu (après l'en-tête)
+    start: u64,
+    /// Fin de la boîte
+    end: u64,
+}
+
+/// Liste les boîtes contenues dans l'intervalle [start, end)
+fn read_boxes<R: Read + Seek>(r: &mut R, start: u64, end: u64) -> Result<Vec<BoxHeader>> {
+    let mut boxes = Vec::new();
+    let mut pos = start;
+
+    while pos + 8 <= end && boxes.len() < MAX_BOXES {
+        r.seek(SeekFrom::Start(pos))?;
+        let mut h = [0u8; 8];
+        if read_up_to(r, &mut h)? < 8 {
+            break;
+        }
+        let size32 = u32::from_be_bytes([h[0], h[1], h[2], h[3]]) as u64;
+        let typ = [h[4], h[5], h[6], h[7]];
+
+        let (header_len, size) = match size32 {
+            0 => (8, end - pos),
+            1 => (16, read_u64_be(r)?),
+            s => (8, s),
+        };
+        if size < header_len {
+            bail!("invalid box size");
+        }
+
+        // Tailles lues dans le fichier: un `largesize` forgé ne doit pas déborder
+        let box_end = match pos.checked_add(size) {
+            Some(e) => e.min(end),
+            None => bail!("invalid box size"),
+        };
+        boxes.push(BoxHeader {
+            typ,
+            start: pos + header_len,
+            end: box_end,
+        });
+        pos = box_end;
+    }
+    Ok(boxes)
+}
+
+/// Cherche la première boîte d'un type donné
+fn find_box<'a>(boxes: &'a [BoxHeader], typ: &[u8; 4]) -> Option<&'a BoxHeader> {
+    boxes.iter().find(|b| &b.typ == typ)
+}
+
+/// Lit les dates de création `mvhd`, `tkhd` et `mdhd` d'un MP4/MOV, et la clé
+/// `CreationDate` des vidéos iPhone
+fn read_quicktime<R: Read + Seek>(r: &mut R) -> Result<MediaMetadata> {
+    let file_end = r.seek(SeekFrom::End(0))?;
+    let top = read_boxes(r, 0, file_end)?;
+
+    let mut meta = MediaMetadata::default();
+    let moov = match find_box(&top, b"moov") {
+        Some(b) => b,
+        None => return Ok(meta),
+    };
+    let moov_children = read_boxes(r, moov.start, moov.end)?;
+
+    // CR3 Canon: l'EXIF passe avant les dates QuickTime
+    for uuid in moov_children.iter().filter(|b| &b.typ == b"uuid") {
+        read_canon_cr3(r, uuid, &mut meta)?;
+    }
+
+    if let Some(mvhd) = find_box(&moov_children, b"mvhd") {
+        push_quicktime_date(
+            &mut meta,
+            "QuickTime:CreateDate",
+            read_full_box_time(r, mvhd)?,
+        );
+    }
+
+    if let Some(udta_meta) = find_box(&moov_children, b"meta") {
+        meta.content_identifier = read_quicktime_key(r, udta_meta, APPLE_CONTENT_ID_KEY)?;
+        // ISO 8601 (`2024-05-01T10:00:00+0200`), comme exiftool la date est gardée
+        // avec son décalage: c'est elle qui donne l'heure locale de la vidéo
+        let creation = read_quicktime_key(r, udta_meta, APPLE_CREATION_DATE_KEY)?;
+        push_date(
+            &mut meta,
+            "Keys:CreationDate",
+            creation.map(|d| d.replacen('T', " ", 1)),
+        );
+    }
+
+    // Comme exiftool, on ne considère que la première piste (Track1)
+    if let Some(trak) = find_box(&moov_children, b"trak") {
+        let trak_children = read_boxes(r, trak.start, trak.end)?;
+        if let Some(tkhd) = find_box(&trak_children, b"tkhd") {
+            push_quicktime_date(
+                &mut meta,
+                "Track1:TrackCreateDate",
+                read_full_box_time(r, tkhd)?,
+            );
+        }
+        if let Some(mdia) = find_box(&trak_children, b"mdia") {
+            let mdia_children = read_boxes(r, mdia.start, mdia.end)?;
+            if let Some(mdhd) = find_box(&mdia_children, b"mdhd") {
+                push_quicktime_date(
+                    &mut meta,
+                    "Track1:MediaCreateDate",
+                    read_full_box_time(r, mdhd)?,
+                );
+            }
+        }
+    }
+
+    Ok(meta)
+}
+
+/// Lit l'EXIF d'un CR3 Canon: boîtes `CMT1` (IFD0) et `CMT2` (IFD EXIF) de la
+/// boîte `moov/uuid` Canon, chacune étant une structure TIFF complète
+fn read_canon_cr3<R: Read + Seek>(
+    r: &mut R,
+    uuid: &BoxHeader,
+    meta: &mut MediaMetadata,
+) -> Result<()> {
+    let mut id = [0u8; 16];
+    r.seek(SeekFrom::Start(uuid.start))?;
+    if read_up_to(r, &mut id)? < 16 || id != CANON_CR3_UUID {
+        return Ok(());
+    }
+
+    let children = read_boxes(r, uuid.start + 16, uuid.end)?;
+    if let Some(cmt1) = find_box(&children, b"CMT1") {
+        let ifd0 = read_tiff(r, cmt1.start)?;
+        meta.make = ifd0.make;
+        meta.model = ifd0.model;
+        meta.dates.extend(ifd0.dates);
+    }
+    if let Some(cmt2) = find_box(&children, b"CMT2") {
+        let (mut tiff, offset) = open_tiff(r, cmt2.start)?;
+        read_exif_ifd(&mut tiff, offset, meta)?;
+    }
+    Ok(())
+}
+
+/// Lit une valeur texte des métadonnées QuickTime `meta`/`keys`/`ilst` (vidéos iPhone)
+fn read_quicktime_key<R: Read + Seek>(
+    r: &mut R,
+    meta_box: &BoxHeader,
+    wanted: &[u8],
+) -> Result<Option<String>> {
+    // `meta` est une full box en MP4 mais pas en QuickTime: on repère `hdlr`
+    let mut children = read_boxes(r, meta_box.start, meta_box.end)?;
+    if find_box(&children, b"hdlr").is_none() {
+        children = read_boxes(r, meta_box.start + 4, meta_box.end)?;
+    }
+
+    let (keys, ilst) = match (find_box(&children, b"keys"), find_box(&children, b"ilst")) {
+        (Some(k), Some(i)) => (k, i),
+        _ => return Ok(None),
+    };
+
+    // keys: version/flags, nombre d'entrées, puis (taille, espace de noms, nom)
+    r.seek(SeekFrom::Start(keys.start + 4))?;
+    let count = read_u32_be(r)?.min(MAX_BOXES as u32);
+    let mut index = None;
+    let mut pos = keys.start + 8;
+    for i in 1..=count {
+        r.seek(SeekFrom::Start(pos))?;
+        let size = read_u32_be(r)? as u64;
+        if size < 8 || pos + size > keys.end {
+            break;
+        }
+        let mut name = vec![0u8; (size - 8) as usize];
+        r.seek(SeekFrom::Current(4))?;
+        r.read_exact(&mut name)?;
+        if name == wanted {
+            index = Some(i);
+            break;
+        }
+        pos += size;
+    }
+    let Some(index) = index else {
+        return Ok(None);
+    };
+
+    // ilst: une boîte par valeur, dont le type est l'index (1-based) de la clé
+    for item in read_boxes(r, ilst.start, ilst.end)? {
+        if u32::from_be_bytes(item.typ) != index {
+            continue;
+        }
+        for data in read_boxes(r, item.start, item.end)? {
+            // data: type de valeur (1 = UTF-8), locale, puis la valeur
+            if &data.typ != b"data" || data.end < data.start + 8 {
+                continue;
+            }
+            let len = (data.end - data.start - 8).min(256) as usize;
+            let mut value = vec![0u8; len];
+            r.seek(SeekFrom::Start(data.start + 8))?;
+            r.read_exact(&mut value)?;
+            let value = String::from_utf8_lossy(&value).trim().to_string();
+            return Ok((!value.is_empty()).then_some(value));
+        }
+    }
+    Ok(None)
+}
+
+/// Lit le champ `creation_time` d'une boîte `mvhd`/`tkhd`/`mdhd` (version 0 ou 1)
+fn read_full_box_time<R: Read + Seek>(r: &mut R, b: &BoxHeader) -> Result<u64> {
+    r.seek(SeekFrom::Start(b.start))?;
+    let mut version = [0u8; 4];
+    r.read_exact(&mut version)?;
+    if version[0] == 1 {
+        read_u64_be(r)
+    } else {
+        Ok(read_u32_be(r)? as u64)
+    }
+}
+
+/// Ajoute une date QuickTime (secondes UTC depuis 1904), laissée en UTC comme le
+/// fait exiftool (la conversion en heure locale est faite par `capture_time`)
+fn push_quicktime_date(meta: &mut MediaMetadata, tag: &str, secs: u64) {
+    // 0 = date non renseignée
+    if secs == 0 {
+        return;
+    }
+    let Some(unix) = i64::try_from(secs)
+        .ok()
+        .and_then(|s| s.checked_sub(QUICKTIME_EPOCH_OFFSET))
+    else {
+        return;
+    };
+    let utc: DateTime<Utc> = match Utc.timestamp_opt(unix, 0).single() {
+        Some(dt) => dt,
+        None => return,
+    };
+    let raw = utc.format("%Y:%m:%d %H:%M:%S").to_string();
+    push_date(meta, tag, Some(raw));
+}
+
+/// Extrait le bloc EXIF d'un fichier HEIF (item `Exif` référencé par `iinf`/`iloc`)
+fn read_heif<R: Read + Seek>(r: &mut R) -> Result<MediaMetadata> {
+    let file_end = r.seek(SeekFrom::End(0))?;
+    let top = read_boxes(r, 0, file_end)?;
+
+    let meta_box = match find_box(&top, b"meta") {
+        Some(b) => b,
+        None => return Ok(MediaMetadata::default()),
+    };
+    // `meta` est une full box: 4 octets version/flags avant les enfants
+    let children = read_boxes(r, meta_box.start + 4, meta_box.end)?;
+
+    let exif_id = match find_box(&children, b"iinf") {
+        Some(iinf) => find_exif_item(r, iinf)?,
+        None => None,
+    };
+    let Some(exif_id) = exif_id else {
+        return Ok(MediaMetadata::default());
+    };
+
+    let extent = match find_box(&children, b"iloc") {
+        Some(iloc) => find_item_extent(r, iloc, exif_id)?,
+        None => None,
+    };
+    let Some((offset, length)) = extent else {
+        return Ok(MediaMetadata::default());
+    };
+    if !(4..=MAX_EXIF_SIZE).contains(&length) {
+        bail!("invalid HEIF Exif item size");
+    }
+
+    let mut data = vec![0u8; length as usize];
+    r.seek(SeekFrom::Start(offset))?;
+    r.read_exact(&mut data)
+        .context("truncated HEIF Exif item")?;
+
+    // Les 4 premiers octets donnent le décalage vers l'en-tête TIFF
+    let tiff_offset = 4 + u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as u64;
+    read_tiff(&mut Cursor::new(&data), tiff_offset)
+}
+
+/// Cherche l'identifiant de l'item de type `Exif` dans la boîte `iinf`
+fn find_exif_item<R: Read + Seek>(r: &mut R, iinf: &BoxHeader) -> Result<Option<u32>> {
+    r.seek(SeekFrom::Start(iinf.start))?;
+    let mut vf = [0u8; 4];
+    r.read_exact(&mut vf)?;
+    let entries_start = if vf[0] == 0 {
+        iinf.start + 6
+    } else {
+        iinf.start + 8
+    };
+
+    for infe in read_boxes(r, entries_start, iinf.end)? {
+        if &infe.typ != b"infe" {
+            continue;
+        }
+        r.seek(SeekFrom::Start(infe.start))?;
+        r.read_exact(&mut vf)?;
+        let version = vf[0];
+        if version < 2 {
+            continue;
+        }
+        let item_id = if version == 2 {
+            read_u16_be(r)? as u32
+        } else {
+            read_u32_be(r)?
+        };
+        let _protection_index = read_u16_be(r)?;
+        let mut item_type = [0u8; 4];
+        r.read_exact(&mut item_type)?;
+        if &item_type == b"Exif" {
+            return Ok(Some(item_id));
+        }
+    }
+    Ok(None)
+}
+
+/// Retourne (offset, longueur) du premier extent d'un item dans la boîte `iloc`
+fn find_item_extent<R: Read + Seek>(
+    r: &mut R,
+    iloc: &BoxHeader,
+    wanted: u32,
+) -> Result<Option<(u64, u64)>> {
+    r.seek(SeekFrom::Start(iloc.start))?;
+    let mut vf = [0u8; 4];
+    r.read_exact(&mut vf)?;
+    let version = vf[0];
+
+    let mut sizes = [0u8; 2];
+    r.read_exact(&mut sizes)?;
+    let offset_size = sizes[0] >> 4;
+    let length_size = sizes[0] & 0x0F;
+    let base_offset_size = sizes[1] >> 4;
+    let index_size = if version >= 1 { sizes[1] & 0x0F } else { 0 };
+
+    let item_count = if version < 2 {
+        read_u16_be(r)? as u32
+    } else {
+        read_u32_be(r)?
+    };
+
+    for _ in 0..item_count {
+        let item_id = if version < 2 {
+            read_u16_be(r)? as u32
+        } else {
+            read_u32_be(r)?
+        };
+        let mut construction_method = 0;
+        if version >= 1 {
+            construction_method = read_u16_be(r)? & 0x0F;
+        }
+        let _data_ref = read_u16_be(r)?;
+        let base_offset = read_sized(r, base_offset_size)?;
+        let extent_count = read_u16_be(r)?;
+
+        let mut first = None;
+        for _ in 0..extent_count {
+            if index_size > 0 {
+                read_sized(r, index_size)?;
+            }
+            let off = read_sized(r, offset_size)?;
+            let len = read_sized(r, length_size)?;
+            let Some(start) = base_offset.checked_add(off) else {
+                bail!("invalid iloc extent offset");
+            };
+            first.get_or_insert((start, len));
+        }
+
+        // Seule la méthode 0 (offset dans le fichier) est supportée
+        if item_id == wanted && construction_method == 0 {
+            return Ok(first);
+        }
+    }
+    Ok(None)
+}
+
+// --- Lecture binaire -----------------------------------------------------------
+
+/// Lit autant d'octets que possible (jusqu'à remplir `buf` ou atteindre EOF)
+fn read_up_to<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match r.read(&mut buf[n..])? {
+            0 => break,
+            k => n += k,
+        }
+    }
+    Ok(n)
+}
+
+fn read_u16_be<R: Read>(r: &mut R) -> Result<u16> {
+    let mut b = [0u8; 2];
+    r.read_exact(&mut b)?;
+    Ok(u16::from_be_bytes(b))
+}
+
+fn read_u32_be<R: Read>(r: &mut R) -> Result<u32> {
+    let mut b = [0u8; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_be_bytes(b))
+}
+
+fn read_u64_be<R: Read>(r: &mut R) -> Result<u64> {
+    let mut b = [0u8; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_be_bytes(b))
+}
+
+/// Lit un entier big-endian de 0, 4 ou 8 octets (champs de taille variable de `iloc`)
+fn read_sized<R: Read>(r: &mut R, size: u8) -> Result<u64> {
+    match size {
+        0 => Ok(0),
+        4 => Ok(read_u32_be(r)? as u64),
+        8 => read_u64_be(r),
+        _ => bail!("unsupported iloc field size: {}", size),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDateTime};
+
+    /// Entrée d'IFD little-endian: tag, type, nombre, valeur ou offset
+    fn ifd_entry(out: &mut Vec<u8>, tag: u16, typ: u16, count: u32, value: [u8; 4]) {
+        out.extend(tag.to_le_bytes());
+        out.extend(typ.to_le_bytes());
+        out.extend(count.to_le_bytes());
+        out.extend(value);
+    }
+
+    /// TIFF little-endian: IFD0 (Make, pointeur EXIF) et IFD EXIF (DateTimeOriginal,
+    /// SubSecTimeOriginal)
+    fn tiff() -> Vec<u8> {
+        let mut t = b"II\x2A\x00\x08\x00\x00\x00".to_vec();
+        // IFD0 à 8 (30 octets), IFD EXIF à 38 (30 octets), données à 68
+        t.extend(2u16.to_le_bytes());
+        ifd_entry(&mut t, 0x010F, 2, 6, 68u32.to_le_bytes());
+        ifd_entry(&mut t, 0x8769, 4, 1, 38u32.to_le_bytes());
+        t.extend(0u32.to_le_bytes());
+        t.extend(2u16.to_le_bytes());
+        ifd_entry(&mut t, 0x9003, 2, 20, 74u32.to_le_bytes());
+        ifd_entry(&mut t, 0x9291, 2, 4, *b"045\0");
+        t.extend(0u32.to_le_bytes());
+        t.extend(b"Canon\0");
+        t.extend(b"2025:07:23 08:54:04\0");
+        t
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend(tiff());
+        let mut j = vec![0xFF, 0xD8];
+        // Segment APP0 ignoré avant l'EXIF
+        j.extend([0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+        j.extend([0xFF, 0xE1]);
+        j.extend((payload.len() as u16 + 2).to_be_bytes());
+        j.extend(payload);
+        j.extend([0xFF, 0xD9]);
+        j
+    }
+
+    /// Boîte ISOBMFF (taille 32 bits)
+    fn bmff_box(typ: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut b = (content.len() as u32 + 8).to_be_bytes().to_vec();
+        b.extend(typ);
+        b.extend(content);
+        b
+    }
+
+    /// Boîte complète (version 0) dont le contenu commence par une date QuickTime
+    fn time_box(typ: &[u8; 4], secs: u32) -> Vec<u8> {
+        let mut content = vec![0u8; 4];
+        content.extend(secs.to_be_bytes());
+        content.extend(secs.to_be_bytes());
+        bmff_box(typ, &content)
+    }
+
+    /// 2025-07-23 08:54:04 UTC en secondes depuis 1904
+    const QT_SECS: u32 = 3_836_105_644;
+
+    fn date<'a>(meta: &'a MediaMetadata, tag: &str) -> Option<&'a str> {
+        meta.dates
+            .iter()
+            .find(|d| d.tag == tag)
+            .map(|d| d.raw.as_str())
+    }
+
+    #[test]
+    fn sniffs_formats() {
+        assert!(matches!(sniff(&jpeg()[..12]), Some(Format::Jpeg)));
+        assert!(matches!(sniff(&tiff()[..12]), Some(Format::Tiff)));
+        assert!(matches!(sniff(b"\0\0\0\x18ftypheic"), Some(Format::Heif)));
+        assert!(matches!(
+            sniff(b"\0\0\0\x18ftypisom"),
+            Some(Format::QuickTime)
+        ));
+        assert!(sniff(b"not a media").is_none());
+    }
+
+    #[test]
+    fn reads_jpeg_exif() {
+        let meta = read_jpeg(&mut Cursor::new(jpeg()), 0).unwrap();
+        assert_eq!(meta.make.as_deref(), Some("Canon"));
+        assert_eq!(
+            date(&meta, "ExifIFD:DateTimeOriginal"),
+            Some("2025:07:23 08:54:04")
+        );
+        assert_eq!(meta.sub_sec_time_original.as_deref(), Some("045"));
+    }
+
+    #[test]
+    fn jpeg_without_exif_has_no_dates() {
+        let j = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x02, 0xFF, 0xD9];
+        let meta = read_jpeg(&mut Cursor::new(j), 0).unwrap();
+        assert!(meta.dates.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_jpeg() {
+        let mut j = jpeg();
+        j.truncate(20);
+        assert!(read_jpeg(&mut Cursor::new(j), 0).is_err());
+    }
+
+    #[test]
+    fn reads_quicktime_dates() {
+        let trak = bmff_box(
+            b"trak",
+            &[
+                time_box(b"tkhd", QT_SECS),
+                bmff_box(b"mdia", &time_box(b"mdhd", QT_SECS)),
+            ]
+            .concat(),
+        );
+        let moov = bmff_box(b"moov", &[time_box(b"mvhd", QT_SECS), trak].concat());
+        let file = [bmff_box(b"ftyp", b"isom\0\0\0\0"), moov].concat();
+
+        let meta = read_quicktime(&mut Cursor::new(file)).unwrap();
+        for tag in [
+            "QuickTime:CreateDate",
+            "Track1:TrackCreateDate",
+            "Track1:MediaCreateDate",
+        ] {
+            assert_eq!(date(&meta, tag), Some("2025:07:23 08:54:04"), "{}", tag);
+        }
+    }
+
+    /// Boîte `meta` QuickTime (sans version/flags) avec une clé `mdta` et sa valeur UTF-8
+    fn keys_meta(key: &[u8], value: &[u8]) -> Vec<u8> {
+        let mut keys = vec![0u8; 4];
+        keys.extend(1u32.to_be_bytes());
+        keys.extend((key.len() as u32 + 8).to_be_bytes());
+        keys.extend(b"mdta");
+        keys.extend(key);
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend([0u8; 4]);
+        data.extend(value);
+        let item = bmff_box(&1u32.to_be_bytes(), &bmff_box(b"data", &data));
+        bmff_box(
+            b"meta",
+            &[
+                bmff_box(b"hdlr", &[0u8; 24]),
+                bmff_box(b"keys", &keys),
+                bmff_box(b"ilst", &item),
+            ]
+            .concat(),
+        )
+    }
+
+    #[test]
+    fn reads_iphone_creation_date_with_offset() {
+        let meta_box = keys_meta(APPLE_CREATION_DATE_KEY, b"2025-07-23T10:54:04+0200");
+        let moov = bmff_box(b"moov", &[time_box(b"mvhd", QT_SECS), meta_box].concat());
+
+        let meta = read_quicktime(&mut Cursor::new(moov)).unwrap();
+        let creation = meta
+            .dates
+            .iter()
+            .find(|d| d.tag == "Keys:CreationDate")
+            .expect("CreationDate");
+        assert_eq!(
+            creation.value,
+            NaiveDateTime::parse_from_str("2025-07-23 10:54:04", "%Y-%m-%d %H:%M:%S").unwrap()
+        );
+        assert_eq!(creation.offset, FixedOffset::east_opt(2 * 3600));
+        assert_eq!(
+            date(&meta, "QuickTime:CreateDate"),
+            Some("2025:07:23 08:54:04")
+        );
+        assert!(meta.content_identifier.is_none());
+    }
+
+    #[test]
+    fn ignores_unset_quicktime_dates() {
+        let moov = bmff_box(b"moov", &time_box(b"mvhd", 0));
+        let meta = read_quicktime(&mut Cursor::new(moov)).unwrap();
+        assert!(meta.dates.is_empty());
+    }
+
+    #[test]
+    fn rejects_overflowing_box_size() {
+        let mut file = bmff_box(b"free", &[]);
+        file.extend(1u32.to_be_bytes());
+        file.extend(b"moov");
+        file.extend(u64::MAX.to_be_bytes());
+        file.extend([0u8; 16]);
+        assert!(read_quicktime(&mut Cursor::new(file)).is_err());
+    }
+
+    /// iloc version 0 (offsets, longueurs et base sur 8 octets), un item à un extent
+    fn iloc(base_offset: u64, offset: u64) -> (Vec<u8>, BoxHeader) {
+        let mut iloc = vec![0u8; 4];
+        iloc.extend([0x88, 0x80]);
+        iloc.extend(1u16.to_be_bytes());
+        iloc.extend(1u16.to_be_bytes());
+        iloc.extend(0u16.to_be_bytes());
+        iloc.extend(base_offset.to_be_bytes());
+        iloc.extend(1u16.to_be_bytes());
+        iloc.extend(offset.to_be_bytes());
+        iloc.extend(4u64.to_be_bytes());
+        let header = BoxHeader {
+            typ: *b"iloc",
+            start: 0,
+            end: iloc.len() as u64,
+        };
+        (iloc, header)
+    }
+
+    #[test]
+    fn finds_iloc_extent() {
+        let (data, header) = iloc(100, 20);
+        let extent = find_item_extent(&mut Cursor::new(data), &header, 1).unwrap();
+        assert_eq!(extent, Some((120, 4)));
+    }
+
+    #[test]
+    fn rejects_overflowing_iloc_offset() {
+        let (data, header) = iloc(u64::MAX, 1);
+        let err = find_item_extent(&mut Cursor::new(data), &header, 1).unwrap_err();
+        assert!(err.to_string().contains("iloc"), "{:#}", err);
+    }
+}
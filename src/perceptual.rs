@@ -0,0 +1,220 @@
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::ImageReader;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Extensions des images décodées pour le hash perceptuel
+const PERCEPTUAL_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "tif", "tiff"];
+
+/// Indique si un fichier peut recevoir un hash perceptuel
+pub fn supports_dhash(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|e| PERCEPTUAL_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Calcule le hash perceptuel (dHash 64 bits) d'une image.
+///
+/// L'image est réduite à 9x8 pixels en niveaux de gris; chaque bit indique si un
+/// pixel est plus clair que son voisin de droite. Deux copies d'une même photo
+/// (recompressée, redimensionnée) ont des hashes à faible distance de Hamming.
+///
+/// # Errors
+/// Retourne une erreur si l'image ne peut pas être lue ou décodée
+pub fn dhash(path: &Path) -> Result<u64> {
+    let img = ImageReader::open(path)
+        .with_context(|| format!("open {}", path.display()))?
+        .with_guessed_format()
+        .with_context(|| format!("read {}", path.display()))?
+        .decode()
+        .with_context(|| format!("decode {}", path.display()))?;
+
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | bit as u64;
+        }
+    }
+    Ok(hash)
+}
+
+/// Nombre de bits différents entre deux hashes perceptuels
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Nœud de l'arbre BK: un hash, les fichiers qui l'ont, et les sous-arbres indexés
+/// par leur distance à ce hash
+struct Node {
+    dhash: u64,
+    paths: Vec<PathBuf>,
+    children: Vec<(u32, usize)>,
+}
+
+/// Arbre BK des hashes perceptuels.
+///
+/// Par l'inégalité triangulaire, seuls les sous-arbres à distance `d ± max` du hash
+/// cherché peuvent contenir un voisin: la recherche n'explore qu'une petite partie
+/// de la bibliothèque au lieu de la parcourir entière.
+#[derive(Default)]
+pub struct DhashTree {
+    nodes: Vec<Node>,
+}
+
+impl DhashTree {
+    /// Ajoute un fichier et son hash perceptuel
+    pub fn insert(&mut self, dhash: u64, path: PathBuf) {
+        if self.nodes.is_empty() {
+            self.nodes.push(Node {
+                dhash,
+                paths: vec![path],
+                children: Vec::new(),
+            });
+            return;
+        }
+
+        let mut current = 0;
+        loop {
+            let d = distance(self.nodes[current].dhash, dhash);
+            if d == 0 {
+                self.nodes[current].paths.push(path);
+                return;
+            }
+            match self.nodes[current].children.iter().find(|(k, _)| *k == d) {
+                Some(&(_, child)) => current = child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(Node {
+                        dhash,
+                        paths: vec![path],
+                        children: Vec::new(),
+                    });
+                    self.nodes[current].children.push((d, child));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Retire un fichier (le nœud reste, pour ne pas couper ses sous-arbres)
+    pub fn remove(&mut self, dhash: u64, path: &Path) {
+        let mut current = 0;
+        while let Some(node) = self.nodes.get_mut(current) {
+            let d = distance(node.dhash, dhash);
+            if d == 0 {
+                node.paths.retain(|p| p != path);
+                return;
+            }
+            match node.children.iter().find(|(k, _)| *k == d) {
+                Some(&(_, child)) => current = child,
+                None => return,
+            }
+        }
+    }
+
+    /// Cherche le fichier le plus proche d'un hash perceptuel.
+    ///
+    /// # Returns
+    /// Le fichier et sa distance, si elle ne dépasse pas `max_distance` (à distance
+    /// égale, le premier chemin dans l'ordre alphabétique)
+    pub fn nearest(&self, dhash: u64, max_distance: u32) -> Option<(&Path, u32)> {
+        let mut best: Option<(&Path, u32)> = None;
+        let mut stack = vec![0];
+        while let Some(i) = stack.pop() {
+            let Some(node) = self.nodes.get(i) else {
+                continue;
+            };
+            let d = distance(node.dhash, dhash);
+            if d <= max_distance {
+                for path in &node.paths {
+                    let better = match best {
+                        Some((p, bd)) => (d, path.as_path()) < (bd, p),
+                        None => true,
+                    };
+                    if better {
+                        best = Some((path, d));
+                    }
+                }
+            }
+            let (lo, hi) = (d.saturating_sub(max_distance), d + max_distance);
+            stack.extend(
+                node.children
+                    .iter()
+                    .filter(|(k, _)| (lo..=hi).contains(k))
+                    .map(|(_, child)| *child),
+            );
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(entries: &[(u64, &str)]) -> DhashTree {
+        let mut tree = DhashTree::default();
+        for (dhash, path) in entries {
+            tree.insert(*dhash, PathBuf::from(path));
+        }
+        tree
+    }
+
+    #[test]
+    fn finds_nearest_within_distance() {
+        let t = tree(&[(0, "a.jpg"), (0b1111, "b.jpg"), (u64::MAX, "c.jpg")]);
+        assert_eq!(t.nearest(0b1, 2), Some((Path::new("a.jpg"), 1)));
+        assert_eq!(t.nearest(0b0111, 2), Some((Path::new("b.jpg"), 1)));
+        assert_eq!(t.nearest(u64::MAX << 1, 2), Some((Path::new("c.jpg"), 1)));
+        assert_eq!(t.nearest(0xFF00, 3), None);
+        assert_eq!(DhashTree::default().nearest(0, 64), None);
+    }
+
+    #[test]
+    fn matches_brute_force_search() {
+        // Hashes pseudo-aléatoires proches les uns des autres
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        let mut entries = Vec::new();
+        for i in 0..200 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            entries.push((x & 0xFFFF, format!("{:03}.jpg", i)));
+        }
+        let mut t = DhashTree::default();
+        for (dhash, path) in &entries {
+            t.insert(*dhash, PathBuf::from(path));
+        }
+        for query in [0u64, 0x0F0F, 0xFFFF, 0x1234] {
+            for max in [0, 2, 5] {
+                let expected = entries
+                    .iter()
+                    .map(|(h, p)| (distance(*h, query), p.as_str()))
+                    .filter(|(d, _)| *d <= max)
+                    .min()
+                    .map(|(d, p)| (Path::new(p), d));
+                assert_eq!(t.nearest(query, max), expected, "{:#x} {}", query, max);
+            }
+        }
+    }
+
+    #[test]
+    fn ties_go_to_the_first_path() {
+        let t = tree(&[(0b01, "z.jpg"), (0b10, "m.jpg"), (0b10, "a.jpg")]);
+        assert_eq!(t.nearest(0, 1), Some((Path::new("a.jpg"), 1)));
+    }
+
+    #[test]
+    fn removed_paths_are_not_found() {
+        let mut t = tree(&[(0, "a.jpg"), (0b1, "b.jpg"), (0b11, "c.jpg")]);
+        t.remove(0, Path::new("a.jpg"));
+        assert_eq!(t.nearest(0, 1), Some((Path::new("b.jpg"), 1)));
+        // Le nœud retiré garde ses sous-arbres
+        assert_eq!(t.nearest(0b11, 0), Some((Path::new("c.jpg"), 0)));
+        t.remove(0b1, Path::new("b.jpg"));
+        assert_eq!(t.nearest(0, 1), None);
+    }
+}
@@ -0,0 +1,444 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::filesystem::{blake3_file, move_or_copy, rollback_group, transfer, Applied};
+use crate::journal::{Journal, JournalAction, JournalEntry};
+use crate::metadata::CaptureTime;
+
+/// Version du format de plan
+const PLAN_VERSION: u32 = 1;
+
+/// Opération planifiée sur un fichier du dépôt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEntry {
+    pub action: JournalAction,
+    pub src: PathBuf,
+    /// Destination dans la bibliothèque; pour `trash`, le fichier identique déjà classé
+    pub dest: PathBuf,
+    /// Hash BLAKE3 (hexadécimal) et taille de la source au moment du plan
+    pub hash: String,
+    pub size: u64,
+    /// Date de capture retenue (ISO 8601, avec le décalage UTC s'il est connu)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured: Option<String>,
+    /// Date de capture avant correction `--shift` (si une règle s'est appliquée)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_original: Option<String>,
+}
+
+impl PlanEntry {
+    pub fn new(
+        action: JournalAction,
+        src: &Path,
+        dest: &Path,
+        hash: &blake3::Hash,
+        size: u64,
+        capture: Option<&CaptureTime>,
+    ) -> Self {
+        Self {
+            action,
+            src: src.to_path_buf(),
+            dest: dest.to_path_buf(),
+            hash: hash.to_hex().to_string(),
+            size,
+            captured: capture.map(CaptureTime::to_iso8601),
+            captured_original: capture.and_then(CaptureTime::original_iso8601),
+        }
+    }
+}
+
+/// Fichiers à classer ensemble (média principal et compagnons)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanGroup {
+    pub entries: Vec<PlanEntry>,
+}
+
+/// Plan d'exécution (`mecla plan`), relu et exécuté tel quel par `mecla apply`
+#[derive(Debug, Serialize, Deserialize)]
+struct Plan {
+    version: u32,
+    /// Horodatage RFC 3339 de la création du plan
+    created: String,
+    input: PathBuf,
+    output: PathBuf,
+    groups: Vec<PlanGroup>,
+}
+
+/// Groupes planifiés pendant une exécution `mecla plan`
+#[derive(Default)]
+pub struct PlanRecorder {
+    groups: Mutex<Vec<PlanGroup>>,
+}
+
+impl PlanRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ajoute les opérations d'un groupe (ignoré s'il n'y a rien à faire)
+    pub fn add(&self, entries: Vec<PlanEntry>) {
+        if entries.is_empty() {
+            return;
+        }
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        groups.push(PlanGroup { entries });
+    }
+
+    /// Écrit le plan (JSON lisible), groupes triés par source.
+    ///
+    /// # Returns
+    /// Le nombre d'opérations planifiées
+    ///
+    /// # Errors
+    /// Retourne une erreur si le fichier ne peut pas être écrit
+    pub fn write(&self, path: &Path, input: &Path, output: &Path) -> Result<usize> {
+        let mut groups = self
+            .groups
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        groups.sort_by(|a, b| a.entries[0].src.cmp(&b.entries[0].src));
+        let count = groups.iter().map(|g| g.entries.len()).sum();
+
+        // Chemins absolus: le plan peut être exécuté depuis un autre dossier
+        for entry in groups.iter_mut().flat_map(|g| &mut g.entries) {
+            entry.src = absolute(&entry.src)?;
+            entry.dest = absolute(&entry.dest)?;
+        }
+        let plan = Plan {
+            version: PLAN_VERSION,
+            created: Local::now().to_rfc3339(),
+            input: absolute(input)?,
+            output: absolute(output)?,
+            groups,
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("create_dir_all {}", parent.display()))?;
+        }
+        let mut w = BufWriter::new(
+            File::create(path).with_context(|| format!("create plan {}", path.display()))?,
+        );
+        serde_json::to_writer_pretty(&mut w, &plan).context("serialize plan")?;
+        w.write_all(b"\n")?;
+        w.flush()
+            .with_context(|| format!("write plan {}", path.display()))?;
+        Ok(count)
+    }
+}
+
+/// Exécute un plan écrit par `mecla plan`.
+///
+/// Chaque groupe est vérifié avant d'être exécuté: un groupe dont une source a
+/// changé depuis le plan (absente, taille ou contenu différent) ou dont une
+/// destination est désormais occupée est refusé en entier, de même qu'un doublon
+/// dont la copie classée a disparu ou changé. Les opérations sont
+/// journalisées comme une exécution normale (`mecla undo`).
+///
+/// # Arguments
+/// * `path` - Fichier de plan
+/// * `dry_run` - Si true, vérifie le plan et affiche les opérations sans les réaliser
+///
+/// # Errors
+/// Retourne une erreur si le plan est illisible, ou si des groupes ont été refusés
+/// ou ont échoué
+pub fn apply(path: &Path, dry_run: bool) -> Result<()> {
+    let f = File::open(path).with_context(|| format!("open plan {}", path.display()))?;
+    let plan: Plan = serde_json::from_reader(BufReader::new(f))
+        .with_context(|| format!("invalid plan {}", path.display()))?;
+    if plan.version != PLAN_VERSION {
+        bail!(
+            "unsupported plan version {} (expected {})",
+            plan.version,
+            PLAN_VERSION
+        );
+    }
+
+    let journal = Journal::new(&plan.output, &plan.input);
+    let applied = AtomicUsize::new(0);
+    let refused = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+
+    // Copies planifiées dans la bibliothèque (pas encore écrites en dry-run)
+    let planned: HashSet<&Path> = plan
+        .groups
+        .iter()
+        .flat_map(|g| &g.entries)
+        .filter(|e| e.action != JournalAction::Trash)
+        .map(|e| e.dest.as_path())
+        .collect();
+
+    // Les doublons d'un fichier classé par le même plan passent après les transferts
+    let (trashing, transfers): (Vec<&PlanGroup>, Vec<&PlanGroup>) = plan
+        .groups
+        .iter()
+        .partition(|g| g.entries.iter().any(|e| e.action == JournalAction::Trash));
+
+    for phase in [transfers, trashing] {
+        phase.par_iter().for_each(|group| {
+            if let Err(e) = check_group(group, &planned, dry_run) {
+                log::warn!("[REFUSE] {}: {:#}", group.entries[0].src.display(), e);
+                refused.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            match apply_group(group, &journal, dry_run) {
+                Ok(()) => {
+                    applied.fetch_add(group.entries.len(), Ordering::Relaxed);
+                }
+                Err(e) => {
+                    log::error!("{}: {:#}", group.entries[0].src.display(), e);
+                    failed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    let (refused, failed) = (refused.into_inner(), failed.into_inner());
+    println!("\n=== Apply ===");
+    println!("Operations applied: {}", applied.into_inner());
+    println!("Groups refused (changed since planning): {}", refused);
+    println!("Errors: {}", failed);
+
+    if !journal.is_empty() {
+        println!(
+            "Journal: {} (revert with: mecla undo \"{}\")",
+            journal.path().display(),
+            journal.path().display()
+        );
+    }
+
+    if refused + failed > 0 {
+        bail!("{} group(s) of the plan were not applied", refused + failed);
+    }
+    Ok(())
+}
+
+/// Vérifie qu'un groupe peut encore être exécuté tel que planifié
+///
+/// # Arguments
+/// * `planned` - Destinations des transferts du plan
+/// * `dry_run` - Les transferts planifiés n'ont pas eu lieu
+fn check_group(group: &PlanGroup, planned: &HashSet<&Path>, dry_run: bool) -> Result<()> {
+    for entry in &group.entries {
+        let md = fs::metadata(&entry.src)
+            .with_context(|| format!("source missing: {}", entry.src.display()))?;
+        if md.len() != entry.size {
+            bail!("source size changed: {}", entry.src.display());
+        }
+        let hash = blake3_file(&entry.src).with_context(|| "hash source")?;
+        if hash.to_hex().as_str() != entry.hash {
+            bail!("source content changed: {}", entry.src.display());
+        }
+
+        if entry.action == JournalAction::Trash {
+            // Le doublon n'est supprimé que si la copie classée est toujours là, intacte
+            // (en dry-run, celle d'un transfert du plan n'est pas encore écrite)
+            if dry_run && planned.contains(entry.dest.as_path()) {
+                continue;
+            }
+            if !entry.dest.exists() {
+                bail!("library copy is gone: {}", entry.dest.display());
+            }
+            let copy = blake3_file(&entry.dest).with_context(|| "hash library copy")?;
+            if copy.to_hex().as_str() != entry.hash {
+                bail!("library copy changed: {}", entry.dest.display());
+            }
+        } else if fs::symlink_metadata(&entry.dest).is_ok() {
+            bail!("destination now exists: {}", entry.dest.display());
+        }
+    }
+    Ok(())
+}
+
+/// Exécute les opérations d'un groupe; en cas d'échec, celles déjà réalisées sont annulées
+fn apply_group(group: &PlanGroup, journal: &Journal, dry_run: bool) -> Result<()> {
+    let mut done = Vec::new();
+    for entry in &group.entries {
+        let result = apply_entry(entry, journal, dry_run);
+        match result {
+            Ok(dest) => done.push(Applied {
+                src: &entry.src,
+                dest,
+                removed_source: entry.action.removed_source(),
+            }),
+            Err(e) => {
+                if !dry_run {
+                    rollback_group(&done);
+                }
+                return Err(e.context(format!("apply {}", entry.src.display())));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Journalise puis exécute une opération du plan
+///
+/// # Returns
+/// L'emplacement du fichier après l'opération (bibliothèque ou corbeille)
+fn apply_entry(entry: &PlanEntry, journal: &Journal, dry_run: bool) -> Result<PathBuf> {
+    let dest = match entry.action {
+        JournalAction::Trash => {
+            log::info!(
+                "[SKIP-DUP] same hash, delete source: {}",
+                entry.src.display()
+            );
+            journal.trash_path(&entry.src)
+        }
+        _ => entry.dest.clone(),
+    };
+
+    if !dry_run {
+        journal.append(&JournalEntry {
+            ts: Local::now().to_rfc3339(),
+            action: entry.action,
+            src: entry.src.clone(),
+            dest: dest.clone(),
+            hash: entry.hash.clone(),
+            captured: entry.captured.clone(),
+            captured_original: entry.captured_original.clone(),
+        })?;
+    }
+
+    match entry.action.transfer_mode() {
+        Some(mode) => transfer(&entry.src, &dest, mode, dry_run)?,
+        None => move_or_copy(&entry.src, &dest, dry_run).with_context(|| "delete source (dup)")?,
+    }
+    Ok(dest)
+}
+
+/// Chemin absolu (sans résoudre les liens symboliques, le fichier peut ne pas exister)
+fn absolute(path: &Path) -> Result<PathBuf> {
+    std::path::absolute(path).with_context(|| format!("absolute path of {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Écrit un fichier et retourne l'opération planifiée correspondante
+    fn entry(action: JournalAction, src: &Path, dest: &Path, content: &[u8]) -> PlanEntry {
+        fs::write(src, content).unwrap();
+        let hash = blake3::hash(content);
+        PlanEntry::new(action, src, dest, &hash, content.len() as u64, None)
+    }
+
+    fn check(entries: Vec<PlanEntry>) -> Result<()> {
+        check_group(&PlanGroup { entries }, &HashSet::new(), false)
+    }
+
+    #[test]
+    fn accepts_unchanged_group() {
+        let dir = tempfile::tempdir().unwrap();
+        let e = entry(
+            JournalAction::Move,
+            &dir.path().join("a.jpg"),
+            &dir.path().join("lib.jpg"),
+            b"photo",
+        );
+        check(vec![e]).unwrap();
+    }
+
+    #[test]
+    fn refuses_changed_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("a.jpg");
+        let e = entry(
+            JournalAction::Move,
+            &src,
+            &dir.path().join("lib.jpg"),
+            b"photo",
+        );
+
+        fs::write(&src, b"other").unwrap();
+        let err = check(vec![e.clone()]).unwrap_err();
+        assert!(
+            err.to_string().contains("source content changed"),
+            "{:#}",
+            err
+        );
+
+        fs::write(&src, b"longer photo").unwrap();
+        let err = check(vec![e.clone()]).unwrap_err();
+        assert!(err.to_string().contains("source size changed"), "{:#}", err);
+
+        fs::remove_file(&src).unwrap();
+        let err = check(vec![e]).unwrap_err();
+        assert!(err.to_string().contains("source missing"), "{:#}", err);
+    }
+
+    #[test]
+    fn refuses_existing_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("lib.jpg");
+        let e = entry(
+            JournalAction::Copy,
+            &dir.path().join("a.jpg"),
+            &dest,
+            b"photo",
+        );
+        fs::write(&dest, b"other").unwrap();
+        let err = check(vec![e]).unwrap_err();
+        assert!(
+            err.to_string().contains("destination now exists"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn refuses_trash_when_library_copy_changed() {
+        let dir = tempfile::tempdir().unwrap();
+        let copy = dir.path().join("lib.jpg");
+        let e = entry(
+            JournalAction::Trash,
+            &dir.path().join("a.jpg"),
+            &copy,
+            b"photo",
+        );
+
+        fs::write(&copy, b"photo").unwrap();
+        check(vec![e.clone()]).unwrap();
+
+        fs::write(&copy, b"edited").unwrap();
+        let err = check(vec![e.clone()]).unwrap_err();
+        assert!(
+            err.to_string().contains("library copy changed"),
+            "{:#}",
+            err
+        );
+
+        fs::remove_file(&copy).unwrap();
+        let err = check(vec![e]).unwrap_err();
+        assert!(
+            err.to_string().contains("library copy is gone"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn dry_run_trusts_planned_library_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let copy = dir.path().join("lib.jpg");
+        let e = entry(
+            JournalAction::Trash,
+            &dir.path().join("a.jpg"),
+            &copy,
+            b"photo",
+        );
+        let group = PlanGroup { entries: vec![e] };
+        let planned = HashSet::from([copy.as_path()]);
+        check_group(&group, &planned, true).unwrap();
+        assert!(check_group(&group, &planned, false).is_err());
+    }
+}
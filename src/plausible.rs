@@ -0,0 +1,133 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use std::sync::LazyLock;
+
+use crate::config::{DEFAULT_MIN_DATE, FUTURE_DATE_TOLERANCE_HOURS, SENTINEL_DATES};
+
+/// `SENTINEL_DATES`, interprétées une fois pour toutes
+static SENTINELS: LazyLock<Vec<NaiveDateTime>> = LazyLock::new(|| {
+    SENTINEL_DATES
+        .iter()
+        .map(|s| {
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").expect("valid sentinel date")
+        })
+        .collect()
+});
+
+/// Filtre des dates de capture invraisemblables (horloge d'appareil non réglée).
+///
+/// Une date est rejetée si elle vaut une date sentinelle (`SENTINEL_DATES`), avant
+/// `--min-date`, après `--max-date` ou dans le futur.
+#[derive(Debug, Clone, Copy)]
+pub struct PlausibleDates {
+    min: NaiveDate,
+    max: Option<NaiveDate>,
+}
+
+impl PlausibleDates {
+    /// Crée le filtre à partir de `--min-date` et `--max-date` (`YYYY-MM-DD`, inclus)
+    ///
+    /// # Errors
+    /// Retourne une erreur si une date est invalide ou si min est après max
+    pub fn new(min: Option<&str>, max: Option<&str>) -> Result<Self> {
+        let min = parse_date("--min-date", min.unwrap_or(DEFAULT_MIN_DATE))?;
+        let max = max.map(|m| parse_date("--max-date", m)).transpose()?;
+        if let Some(max) = max.filter(|max| *max < min) {
+            bail!("--min-date {} is after --max-date {}", min, max);
+        }
+        Ok(Self { min, max })
+    }
+
+    /// Vérifie une date de capture (telle qu'écrite dans le fichier).
+    ///
+    /// # Returns
+    /// None si la date est plausible, sinon la raison du rejet
+    pub fn reject_reason(&self, value: NaiveDateTime) -> Option<String> {
+        if SENTINELS.contains(&value) {
+            return Some("unset camera clock".to_string());
+        }
+        self.out_of_range(value)
+    }
+
+    /// Indique si une date de capture est plausible
+    pub fn accepts(&self, value: NaiveDateTime) -> bool {
+        self.reject_reason(value).is_none()
+    }
+
+    /// Indique si une date est dans l'intervalle accepté, sans tenir compte des dates
+    /// sentinelles (dates de dossiers: `2000` désigne le 1er janvier 2000 à minuit)
+    pub fn in_range(&self, value: NaiveDateTime) -> bool {
+        self.out_of_range(value).is_none()
+    }
+
+    /// Raison du rejet d'une date hors de l'intervalle accepté
+    fn out_of_range(&self, value: NaiveDateTime) -> Option<String> {
+        let day = value.date();
+        if day < self.min {
+            return Some(format!("before --min-date {}", self.min));
+        }
+        if let Some(max) = self.max.filter(|max| day > *max) {
+            return Some(format!("after --max-date {}", max));
+        }
+        let latest = Local::now().naive_local() + Duration::hours(FUTURE_DATE_TOLERANCE_HOURS);
+        if value > latest {
+            return Some("in the future".to_string());
+        }
+        None
+    }
+}
+
+fn parse_date(option: &str, s: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+        .map_err(|e| anyhow!("Invalid {} {:?} (expected YYYY-MM-DD): {}", option, s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dt(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn rejects_exact_sentinels_only() {
+        let p = PlausibleDates::new(None, None).unwrap();
+        for s in SENTINEL_DATES {
+            assert!(!p.accepts(dt(s)), "{}", s);
+            // Les dates de dossiers ne sont soumises qu'à l'intervalle
+            assert!(p.in_range(dt(s)), "{}", s);
+        }
+        assert!(p.accepts(dt("2000-01-01 00:12:30")));
+        assert!(p.accepts(dt("1970-01-01 10:00:00")));
+    }
+
+    #[test]
+    fn applies_the_configured_range() {
+        let p = PlausibleDates::new(Some("2010-01-01"), Some("2020-12-31")).unwrap();
+        assert!(!p.accepts(dt("2009-12-31 23:59:59")));
+        assert!(p.accepts(dt("2010-01-01 00:00:01")));
+        assert!(p.accepts(dt("2020-12-31 23:59:59")));
+        assert!(!p.accepts(dt("2021-01-01 00:00:00")));
+        assert!(p
+            .reject_reason(dt("2021-01-01 00:00:00"))
+            .unwrap()
+            .contains("--max-date"));
+    }
+
+    #[test]
+    fn rejects_future_dates() {
+        let p = PlausibleDates::new(None, None).unwrap();
+        let now = Local::now().naive_local();
+        assert!(p.accepts(now));
+        assert!(p.accepts(now + Duration::hours(FUTURE_DATE_TOLERANCE_HOURS - 1)));
+        assert!(!p.accepts(now + Duration::days(30)));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert!(PlausibleDates::new(Some("2020-13-01"), None).is_err());
+        assert!(PlausibleDates::new(None, Some("2020")).is_err());
+        assert!(PlausibleDates::new(Some("2020-01-01"), Some("2019-01-01")).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::MetadataBackend;
+use crate::exiftool::ExiftoolPool;
+use crate::media::MediaKind;
+use crate::metadata::{
+    ensure_exiftool_available, extract_metadata_batch_with_exiftool, parse_exiftool_json,
+    MediaMetadata,
+};
+use crate::native;
+use crate::plausible::PlausibleDates;
+
+/// Source de métadonnées utilisée pour dater les fichiers.
+///
+/// Les implémentations doivent pouvoir être partagées entre les workers rayon.
+pub trait MetadataProvider: Send + Sync {
+    /// Nom du backend (pour les logs)
+    fn name(&self) -> &'static str;
+
+    /// Extrait les métadonnées d'un lot de fichiers.
+    ///
+    /// Les fichiers pour lesquels rien n'a pu être lu n'ont pas d'entrée
+    /// dans la table retournée.
+    ///
+    /// # Arguments
+    /// * `paths` - Fichiers à analyser (idéalement d'un même dossier)
+    ///
+    /// # Returns
+    /// Les métadonnées de chaque fichier reconnu, indexées par chemin
+    fn extract_batch(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata>;
+}
+
+impl fmt::Debug for dyn MetadataProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MetadataProvider({})", self.name())
+    }
+}
+
+/// Backend exiftool (processus persistants, une requête JSON par lot)
+pub struct ExiftoolProvider {
+    pool: ExiftoolPool,
+}
+
+impl ExiftoolProvider {
+    /// Crée le backend exiftool avec un processus par worker rayon
+    pub fn new() -> Self {
+        Self {
+            pool: ExiftoolPool::new(rayon::current_num_threads()),
+        }
+    }
+}
+
+impl MetadataProvider for ExiftoolProvider {
+    fn name(&self) -> &'static str {
+        "exiftool"
+    }
+
+    fn extract_batch(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        extract_metadata_batch_with_exiftool(&self.pool, paths)
+    }
+}
+
+/// Backend natif (EXIF JPEG/TIFF/HEIC, dates QuickTime MP4/MOV), sans dépendance externe
+pub struct NativeProvider;
+
+impl MetadataProvider for NativeProvider {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn extract_batch(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        let mut out = HashMap::with_capacity(paths.len());
+        for path in paths {
+            match native::read_metadata(path) {
+                Ok(Some(meta)) => {
+                    out.insert(path.clone(), meta);
+                }
+                Ok(None) => {}
+                Err(e) => log::debug!("native reader failed for {}: {:#}", path.display(), e),
+            }
+        }
+        out
+    }
+}
+
+/// Combine deux backends: les fichiers sans date de capture plausible dans le
+/// premier sont envoyés, en un seul lot, au second.
+pub struct FallbackProvider {
+    primary: Box<dyn MetadataProvider>,
+    fallback: Box<dyn MetadataProvider>,
+    plausible: PlausibleDates,
+}
+
+impl FallbackProvider {
+    pub fn new(
+        primary: Box<dyn MetadataProvider>,
+        fallback: Box<dyn MetadataProvider>,
+        plausible: PlausibleDates,
+    ) -> Self {
+        Self {
+            primary,
+            fallback,
+            plausible,
+        }
+    }
+}
+
+impl MetadataProvider for FallbackProvider {
+    fn name(&self) -> &'static str {
+        "auto"
+    }
+
+    fn extract_batch(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        let mut out = self.primary.extract_batch(paths);
+
+        let missing: Vec<PathBuf> = paths
+            .iter()
+            .filter(|p| {
+                out.get(*p)
+                    .and_then(|m| m.capture_date(MediaKind::from_path(p), &self.plausible))
+                    .is_none()
+            })
+            .cloned()
+            .collect();
+
+        if !missing.is_empty() {
+            // Le second backend ne remplace une entrée que s'il a mieux à proposer
+            for (path, meta) in self.fallback.extract_batch(&missing) {
+                if meta
+                    .capture_date(MediaKind::from_path(&path), &self.plausible)
+                    .is_some()
+                    || !out.contains_key(&path)
+                {
+                    out.insert(path, meta);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Backend en mémoire, alimenté par un fichier de fixtures.
+///
+/// Le fichier a le format de sortie `exiftool -json -G1 -n`; les `SourceFile`
+/// relatifs sont résolus par rapport au dossier d'entrée. Permet de tester
+/// les règles de classement sans exiftool.
+pub struct FixtureProvider {
+    entries: HashMap<PathBuf, MediaMetadata>,
+}
+
+impl FixtureProvider {
+    /// Charge un fichier de fixtures JSON
+    ///
+    /// # Arguments
+    /// * `path` - Fichier JSON (format `exiftool -json -G1 -n`)
+    /// * `root` - Dossier de résolution des `SourceFile` relatifs
+    ///
+    /// # Errors
+    /// Retourne une erreur si le fichier est illisible ou mal formé
+    pub fn load(path: &Path, root: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("read metadata fixture {}", path.display()))?;
+
+        let entries = parse_exiftool_json(&json)
+            .with_context(|| format!("parse metadata fixture {}", path.display()))?
+            .into_iter()
+            .map(|(source, meta)| (root.join(source), meta))
+            .collect();
+
+        Ok(Self { entries })
+    }
+}
+
+impl MetadataProvider for FixtureProvider {
+    fn name(&self) -> &'static str {
+        "fixture"
+    }
+
+    fn extract_batch(&self, paths: &[PathBuf]) -> HashMap<PathBuf, MediaMetadata> {
+        paths
+            .iter()
+            .filter_map(|p| self.entries.get(p).map(|m| (p.clone(), m.clone())))
+            .collect()
+    }
+}
+
+/// Construit le backend de métadonnées demandé.
+///
+/// # Arguments
+/// * `backend` - Backend choisi (`--metadata-backend`)
+/// * `fixture` - Fichier de fixtures (obligatoire pour le backend `fixture`)
+/// * `input` - Dossier d'entrée (résolution des chemins des fixtures)
+/// * `plausible` - Filtre des dates (une date rejetée déclenche le backend de secours)
+///
+/// # Errors
+/// Retourne une erreur si exiftool est requis mais absent, ou si les fixtures sont invalides
+pub fn build_provider(
+    backend: MetadataBackend,
+    fixture: Option<&Path>,
+    input: &Path,
+    plausible: PlausibleDates,
+) -> Result<Box<dyn MetadataProvider>> {
+    let provider: Box<dyn MetadataProvider> = match backend {
+        MetadataBackend::Auto => match ensure_exiftool_available() {
+            Ok(()) => Box::new(FallbackProvider::new(
+                Box::new(NativeProvider),
+                Box::new(ExiftoolProvider::new()),
+                plausible,
+            )),
+            Err(e) => {
+                log::warn!("{:#}; using the built-in metadata reader only", e);
+                Box::new(NativeProvider)
+            }
+        },
+        MetadataBackend::Exiftool => {
+            ensure_exiftool_available()?;
+            Box::new(ExiftoolProvider::new())
+        }
+        MetadataBackend::Native => Box::new(NativeProvider),
+        MetadataBackend::Fixture => match fixture {
+            Some(f) => Box::new(FixtureProvider::load(f, input)?),
+            None => bail!("--metadata-backend fixture requires --metadata-fixture"),
+        },
+    };
+    Ok(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Écrit une fixture et la charge, les `SourceFile` relatifs à `root`
+    fn fixture(root: &Path, name: &str, json: serde_json::Value) -> FixtureProvider {
+        let path = root.join(name);
+        fs::write(&path, json.to_string()).unwrap();
+        FixtureProvider::load(&path, root).unwrap()
+    }
+
+    #[test]
+    fn fixture_resolves_source_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = fixture(
+            dir.path(),
+            "fixture.json",
+            serde_json::json!([{ "SourceFile": "a.jpg", "IFD0:Make": "Canon" }]),
+        );
+
+        let (a, b) = (dir.path().join("a.jpg"), dir.path().join("b.jpg"));
+        let metas = provider.extract_batch(&[a.clone(), b.clone()]);
+        assert_eq!(metas.len(), 1);
+        assert_eq!(metas[&a].make.as_deref(), Some("Canon"));
+    }
+
+    #[test]
+    fn fallback_only_fills_missing_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let date = "2024:05:01 10:00:00";
+        let primary = fixture(
+            dir.path(),
+            "primary.json",
+            serde_json::json!([
+                { "SourceFile": "dated.jpg", "ExifIFD:DateTimeOriginal": date, "IFD0:Make": "Canon" },
+                { "SourceFile": "undated.jpg", "IFD0:Make": "Canon" },
+            ]),
+        );
+        let fallback = fixture(
+            dir.path(),
+            "fallback.json",
+            serde_json::json!([
+                { "SourceFile": "dated.jpg", "ExifIFD:DateTimeOriginal": date, "IFD0:Make": "Nikon" },
+                { "SourceFile": "undated.jpg", "ExifIFD:DateTimeOriginal": date, "IFD0:Make": "Nikon" },
+                { "SourceFile": "unknown.jpg", "IFD0:Make": "Nikon" },
+            ]),
+        );
+        let provider = FallbackProvider::new(
+            Box::new(primary),
+            Box::new(fallback),
+            PlausibleDates::new(None, None).unwrap(),
+        );
+
+        let paths: Vec<PathBuf> = ["dated.jpg", "undated.jpg", "unknown.jpg"]
+            .iter()
+            .map(|n| dir.path().join(n))
+            .collect();
+        let metas = provider.extract_batch(&paths);
+        let make = |i: usize| metas[&paths[i]].make.as_deref();
+        assert_eq!(make(0), Some("Canon"));
+        assert_eq!(make(1), Some("Nikon"));
+        assert_eq!(make(2), Some("Nikon"));
+    }
+}
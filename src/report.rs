@@ -0,0 +1,410 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::config::{ReportFormat, TransferMode};
+use crate::metadata::{CaptureTime, GpsPosition};
+
+/// Colonnes du rapport CSV (champs de `ReportRecord`)
+const CSV_COLUMNS: &[&str] = &[
+    "source",
+    "destination",
+    "action",
+    "date",
+    "original_date",
+    "date_source",
+    "hash",
+    "size",
+    "latitude",
+    "longitude",
+    "altitude",
+    "error",
+];
+
+/// Sort d'un fichier du dépôt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportAction {
+    /// Déplacé dans la bibliothèque
+    Moved,
+    /// Copié dans la bibliothèque
+    Copied,
+    /// Lien dur dans la bibliothèque
+    Hardlinked,
+    /// Lien symbolique dans la bibliothèque
+    Symlinked,
+    /// Clone copy-on-write dans la bibliothèque
+    Reflinked,
+    /// Transféré sous un nom suffixé (collision avec un fichier différent)
+    Renamed,
+    /// Déjà dans la bibliothèque, source mise à la corbeille
+    DuplicateDeleted,
+    /// Déjà dans la bibliothèque, source laissée en place (modes non destructifs)
+    DuplicateKept,
+    /// Laissé dans le dépôt (quasi-doublon avec `--near-dup skip`, ou fichier sans date
+    /// avec `--on-missing-date skip`)
+    Skipped,
+    /// Échec du traitement
+    Error,
+}
+
+impl From<TransferMode> for ReportAction {
+    fn from(mode: TransferMode) -> Self {
+        match mode {
+            TransferMode::Move => ReportAction::Moved,
+            TransferMode::Copy => ReportAction::Copied,
+            TransferMode::Hardlink => ReportAction::Hardlinked,
+            TransferMode::Symlink => ReportAction::Symlinked,
+            TransferMode::Reflink => ReportAction::Reflinked,
+        }
+    }
+}
+
+/// Ligne du rapport (un fichier du dépôt)
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportRecord {
+    pub source: PathBuf,
+    /// Fichier de la bibliothèque (destination, ou copie existante pour un doublon)
+    pub destination: Option<PathBuf>,
+    pub action: ReportAction,
+    /// Date de capture retenue (ISO 8601, après `--shift`)
+    pub date: Option<String>,
+    /// Date lue avant correction de l'horloge (`--shift`), si elle a été corrigée
+    pub original_date: Option<String>,
+    /// Origine de la date: tag de métadonnées, `filename:<motif>`, `folder` ou `mtime`
+    pub date_source: Option<String>,
+    /// Hash BLAKE3 (hexadécimal) du contenu
+    pub hash: Option<String>,
+    pub size: Option<u64>,
+    /// Position GPS du média principal (degrés décimaux, altitude en mètres)
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl ReportRecord {
+    /// Fichier traité (transféré, doublon ou laissé en place)
+    pub fn done(
+        source: &Path,
+        destination: Option<&Path>,
+        action: ReportAction,
+        capture: Option<&CaptureTime>,
+        hash: &blake3::Hash,
+        size: u64,
+        gps: Option<&GpsPosition>,
+    ) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            destination: destination.map(Path::to_path_buf),
+            action,
+            date: capture.map(CaptureTime::to_iso8601),
+            original_date: capture.and_then(CaptureTime::original_iso8601),
+            date_source: capture.map(|c| c.source.clone()),
+            hash: Some(hash.to_hex().to_string()),
+            size: Some(size),
+            latitude: gps.map(|g| g.latitude),
+            longitude: gps.map(|g| g.longitude),
+            altitude: gps.and_then(|g| g.altitude),
+            error: None,
+        }
+    }
+
+    /// Fichier en échec
+    pub fn failed(source: &Path, error: &anyhow::Error) -> Self {
+        Self {
+            source: source.to_path_buf(),
+            destination: None,
+            action: ReportAction::Error,
+            date: None,
+            original_date: None,
+            date_source: None,
+            hash: None,
+            size: fs::metadata(source).ok().map(|m| m.len()),
+            latitude: None,
+            longitude: None,
+            altitude: None,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+/// Rapport d'exécution (`--report`): une ligne par fichier du dépôt, pour auditer
+/// un import après coup.
+///
+/// Le rapport final est écrit à la fin de l'exécution. D'ici là, chaque ligne est
+/// aussi ajoutée au fil de l'eau à un rapport partiel (`<rapport>.partial`, lignes
+/// JSON ou CSV), qui reste comme trace d'un import interrompu et est supprimé une
+/// fois le rapport final écrit.
+pub struct Report {
+    path: PathBuf,
+    format: ReportFormat,
+    records: Mutex<Vec<ReportRecord>>,
+    /// Rapport partiel, ouvert au premier ajout (None s'il n'y a rien d'écrit)
+    partial: Mutex<Option<PartialWriter>>,
+}
+
+/// Rapport partiel: une ligne JSON par fichier, ou CSV avec en-tête
+enum PartialWriter {
+    Json(BufWriter<File>),
+    Csv(Box<csv::Writer<File>>),
+}
+
+impl Report {
+    pub fn new(path: &Path, format: ReportFormat) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            format,
+            records: Mutex::new(Vec::new()),
+            partial: Mutex::new(None),
+        }
+    }
+
+    /// Ajoute les lignes d'un groupe de fichiers
+    pub fn add(&self, records: Vec<ReportRecord>) {
+        if let Err(e) = self.append_partial(&records) {
+            log::warn!(
+                "Unable to write partial report {}: {:#}",
+                self.partial_path().display(),
+                e
+            );
+        }
+        let mut all = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        all.extend(records);
+    }
+
+    /// Chemin du rapport partiel
+    pub fn partial_path(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(".partial");
+        PathBuf::from(name)
+    }
+
+    /// Ajoute des lignes au rapport partiel, et les transmet au système
+    fn append_partial(&self, records: &[ReportRecord]) -> Result<()> {
+        let mut partial = self.partial.lock().unwrap_or_else(|e| e.into_inner());
+        let w = match partial.as_mut() {
+            Some(w) => w,
+            None => {
+                let path = self.partial_path();
+                create_parent_dir(&path)?;
+                let f =
+                    File::create(&path).with_context(|| format!("create {}", path.display()))?;
+                partial.insert(match self.format {
+                    ReportFormat::Json => PartialWriter::Json(BufWriter::new(f)),
+                    ReportFormat::Csv => PartialWriter::Csv(Box::new(csv::Writer::from_writer(f))),
+                })
+            }
+        };
+
+        for record in records {
+            match w {
+                PartialWriter::Json(w) => {
+                    serde_json::to_writer(&mut *w, record).context("serialize report record")?;
+                    w.write_all(b"\n")?;
+                }
+                PartialWriter::Csv(w) => w.serialize(record).context("serialize report record")?,
+            }
+        }
+        match w {
+            PartialWriter::Json(w) => w.flush()?,
+            PartialWriter::Csv(w) => w.flush()?,
+        }
+        Ok(())
+    }
+
+    /// Écrit le rapport (remplace le fichier existant), lignes triées par source.
+    ///
+    /// # Errors
+    /// Retourne une erreur si le fichier ne peut pas être écrit
+    pub fn write(&self) -> Result<()> {
+        let mut records = self
+            .records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        records.sort_by(|a, b| a.source.cmp(&b.source));
+
+        create_parent_dir(&self.path)?;
+        let f = File::create(&self.path)
+            .with_context(|| format!("create report {}", self.path.display()))?;
+
+        match self.format {
+            ReportFormat::Json => {
+                let mut w = BufWriter::new(f);
+                serde_json::to_writer_pretty(&mut w, &records).context("serialize report")?;
+                w.write_all(b"\n")?;
+                w.flush()?;
+            }
+            ReportFormat::Csv => {
+                let mut w = csv::Writer::from_writer(f);
+                // L'en-tête n'est écrit qu'avec la première ligne
+                if records.is_empty() {
+                    w.write_record(CSV_COLUMNS)?;
+                }
+                for record in &records {
+                    w.serialize(record).context("serialize report record")?;
+                }
+                w.flush()?;
+            }
+        }
+
+        // Toutes les lignes sont dans le rapport final (watch: les suivantes
+        // repartent dans un nouveau rapport partiel)
+        let mut partial = self.partial.lock().unwrap_or_else(|e| e.into_inner());
+        if partial.take().is_some() {
+            let path = self.partial_path();
+            fs::remove_file(&path).with_context(|| format!("remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Crée le dossier parent d'un fichier du rapport
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create_dir_all {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn records() -> Vec<ReportRecord> {
+        let hash = blake3::hash(b"photo");
+        vec![
+            ReportRecord::done(
+                Path::new("depot/Mariage, \"Paul\"/IMG_0001.jpg"),
+                Some(Path::new("lib/2024/05/2024-05-01 10.00.00.jpg")),
+                ReportAction::Moved,
+                None,
+                &hash,
+                5,
+                Some(&GpsPosition {
+                    latitude: -33.86,
+                    longitude: 151.21,
+                    altitude: None,
+                }),
+            ),
+            ReportRecord::failed(
+                Path::new("depot/A.jpg"),
+                &anyhow::anyhow!("Persistent collision"),
+            ),
+        ]
+    }
+
+    fn report(dir: &Path, name: &str, format: ReportFormat) -> Report {
+        let report = Report::new(&dir.join("out").join(name), format);
+        report.add(records());
+        report
+    }
+
+    #[test]
+    fn json_report_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = report(dir.path(), "report.json", ReportFormat::Json);
+        report.write().unwrap();
+
+        let rows: Vec<Value> =
+            serde_json::from_str(&fs::read_to_string(report.path()).unwrap()).unwrap();
+        // Triées par source
+        assert_eq!(rows[0]["source"], "depot/A.jpg");
+        assert_eq!(rows[0]["action"], "error");
+        assert_eq!(rows[0]["error"], "Persistent collision");
+        assert_eq!(rows[1]["source"], "depot/Mariage, \"Paul\"/IMG_0001.jpg");
+        assert_eq!(rows[1]["action"], "moved");
+        assert_eq!(rows[1]["hash"], blake3::hash(b"photo").to_hex().as_str());
+        assert_eq!(rows[1]["latitude"], -33.86);
+        assert_eq!(rows[1]["altitude"], Value::Null);
+        let keys: Vec<&str> = rows[1]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut columns = CSV_COLUMNS.to_vec();
+        columns.sort();
+        assert_eq!(keys, columns);
+    }
+
+    #[test]
+    fn csv_report_round_trips_with_escaping() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = report(dir.path(), "report.csv", ReportFormat::Csv);
+        report.write().unwrap();
+
+        let text = fs::read_to_string(report.path()).unwrap();
+        assert!(text.contains("\"depot/Mariage, \"\"Paul\"\"/IMG_0001.jpg\""));
+
+        let mut reader = csv::Reader::from_path(report.path()).unwrap();
+        assert_eq!(reader.headers().unwrap(), CSV_COLUMNS.to_vec());
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "depot/A.jpg");
+        assert_eq!(&rows[0][2], "error");
+        assert_eq!(&rows[1][0], "depot/Mariage, \"Paul\"/IMG_0001.jpg");
+        assert_eq!(&rows[1][1], "lib/2024/05/2024-05-01 10.00.00.jpg");
+        assert_eq!(&rows[1][7], "5");
+        assert_eq!(&rows[1][8], "-33.86");
+        assert_eq!(&rows[1][10], "");
+    }
+
+    #[test]
+    fn empty_csv_report_has_a_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = Report::new(&dir.path().join("report.csv"), ReportFormat::Csv);
+        report.write().unwrap();
+        assert_eq!(
+            fs::read_to_string(report.path()).unwrap(),
+            format!("{}\n", CSV_COLUMNS.join(","))
+        );
+    }
+
+    #[test]
+    fn partial_report_is_written_as_records_arrive() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, format) in [
+            ("report.json", ReportFormat::Json),
+            ("report.csv", ReportFormat::Csv),
+        ] {
+            let report = report(dir.path(), name, format);
+            let partial = report.partial_path();
+            assert_eq!(
+                partial,
+                dir.path().join("out").join(format!("{}.partial", name))
+            );
+
+            // Import interrompu avant l'écriture du rapport
+            let text = fs::read_to_string(&partial).unwrap();
+            match format {
+                ReportFormat::Json => {
+                    let rows: Vec<Value> = text
+                        .lines()
+                        .map(|l| serde_json::from_str(l).unwrap())
+                        .collect();
+                    assert_eq!(rows[1]["source"], "depot/A.jpg");
+                }
+                ReportFormat::Csv => {
+                    let mut reader = csv::Reader::from_reader(text.as_bytes());
+                    assert_eq!(reader.headers().unwrap(), CSV_COLUMNS.to_vec());
+                    assert_eq!(reader.records().count(), 2);
+                }
+            }
+
+            report.write().unwrap();
+            assert!(!partial.exists());
+            assert!(report.path().exists());
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use anyhow::{bail, Result};
+use chrono::Duration;
+use std::fmt;
+use std::path::Path;
+
+use crate::metadata::{CaptureTime, DateOrigin, MediaMetadata};
+
+/// Fichiers concernés par une règle de décalage
+#[derive(Debug, Clone)]
+enum ShiftTarget {
+    /// Dossier TAG du dépôt (`tag:<nom>`)
+    Tag(String),
+    /// Appareil: modèle seul ou "marque modèle" (insensible à la casse)
+    Camera(String),
+}
+
+/// Règle de correction de l'horloge d'un appareil (`--shift`).
+///
+/// Syntaxe: `<appareil>=<décalage>` ou `tag:<dossier>=<décalage>`, le décalage
+/// étant signé et composé de jours, heures, minutes et secondes
+/// (ex: `Canon EOS 80D=+1h03m`, `tag:Mariage XYZ=-1d2h30s`).
+#[derive(Clone)]
+pub struct ShiftRule {
+    source: String,
+    target: ShiftTarget,
+    delta: Duration,
+}
+
+impl fmt::Debug for ShiftRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ShiftRule({:?})", self.source)
+    }
+}
+
+impl ShiftRule {
+    /// Parse une règle `--shift`
+    ///
+    /// # Errors
+    /// Retourne une erreur si la règle n'a pas de `=`, de cible ou de décalage valide
+    pub fn parse(rule: &str) -> Result<Self> {
+        let (target, delta) = match rule.rsplit_once('=') {
+            Some((t, d)) => (t.trim(), d.trim()),
+            None => bail!(
+                "invalid --shift {:?}: expected <camera>=<offset> or tag:<folder>=<offset>",
+                rule
+            ),
+        };
+
+        let target = match target.strip_prefix("tag:") {
+            Some(tag) if !tag.trim().is_empty() => ShiftTarget::Tag(tag.trim().to_string()),
+            Some(_) => bail!("invalid --shift {:?}: empty tag folder", rule),
+            None if !target.is_empty() => ShiftTarget::Camera(target.to_string()),
+            None => bail!("invalid --shift {:?}: empty camera model", rule),
+        };
+
+        let delta = match parse_delta(delta) {
+            Some(d) => d,
+            None => bail!(
+                "invalid --shift {:?}: offset must look like +1h03m, -2d or +45s",
+                rule
+            ),
+        };
+
+        Ok(Self {
+            source: rule.to_string(),
+            target,
+            delta,
+        })
+    }
+
+    /// Indique si la règle s'applique au fichier
+    fn matches(&self, tag: Option<&str>, meta: Option<&MediaMetadata>) -> bool {
+        match &self.target {
+            ShiftTarget::Tag(t) => tag.is_some_and(|tag| tag.trim() == t),
+            ShiftTarget::Camera(camera) => {
+                let make = meta.and_then(|m| m.make.as_deref()).map(str::trim);
+                let model = meta.and_then(|m| m.model.as_deref()).map(str::trim);
+                match (make, model) {
+                    (_, None) => false,
+                    (None, Some(model)) => model.eq_ignore_ascii_case(camera),
+                    (Some(make), Some(model)) => {
+                        model.eq_ignore_ascii_case(camera)
+                            || format!("{} {}", make, model).eq_ignore_ascii_case(camera)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applique la première règle de décalage qui correspond au fichier.
+///
+/// Seules les dates lues dans les métadonnées viennent de l'horloge de l'appareil:
+/// une date tirée du nom du fichier, d'un dossier ou de mtime n'est pas corrigée.
+///
+/// # Arguments
+/// * `path` - Fichier concerné (pour les logs)
+/// * `rules` - Règles `--shift`, dans l'ordre de la ligne de commande
+/// * `capture` - Date de capture retenue
+/// * `tag` - Dossier TAG du fichier
+/// * `meta` - Métadonnées du fichier (marque, modèle)
+///
+/// # Returns
+/// La date corrigée (l'heure d'origine est conservée dans `CaptureTime::original`)
+pub fn apply_shift(
+    path: &Path,
+    rules: &[ShiftRule],
+    capture: CaptureTime,
+    tag: Option<&str>,
+    meta: Option<&MediaMetadata>,
+) -> CaptureTime {
+    let rule = match rules.iter().find(|r| r.matches(tag, meta)) {
+        Some(r) => r,
+        None => return capture,
+    };
+
+    if capture.origin != DateOrigin::Metadata {
+        log::info!(
+            "[SHIFT] {}: date from {} is not a camera clock, {:?} not applied",
+            path.display(),
+            capture.source,
+            rule.source
+        );
+        return capture;
+    }
+
+    let corrected = match capture.local.checked_add_signed(rule.delta) {
+        Some(dt) => dt,
+        None => {
+            log::warn!(
+                "[SHIFT] {}: {:?} overflows {}, ignored",
+                path.display(),
+                rule.source,
+                capture.local
+            );
+            return capture;
+        }
+    };
+
+    log::info!(
+        "[SHIFT] {}: {} -> {} ({})",
+        path.display(),
+        capture.local,
+        corrected,
+        rule.source
+    );
+    CaptureTime {
+        original: Some(capture.local),
+        local: corrected,
+        ..capture
+    }
+}
+
+/// Parse un décalage signé (`+1h03m`, `-2d`, `+45s`)
+fn parse_delta(s: &str) -> Option<Duration> {
+    let (sign, rest) = match s.chars().next()? {
+        '+' => (1, &s[1..]),
+        '-' => (-1, &s[1..]),
+        _ => return None,
+    };
+
+    let mut total = 0i64;
+    let mut number = String::new();
+    let mut seen_unit = false;
+    for c in rest.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'd' => 86_400,
+            'h' => 3_600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        let n: i64 = number.parse().ok()?;
+        total = total.checked_add(n.checked_mul(unit)?)?;
+        number.clear();
+        seen_unit = true;
+    }
+
+    if !number.is_empty() || !seen_unit {
+        return None;
+    }
+    Duration::try_seconds(sign * total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn at(h: u32, m: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 7, 23)
+            .unwrap()
+            .and_hms_opt(h, m, s)
+            .unwrap()
+    }
+
+    fn capture(origin: DateOrigin) -> CaptureTime {
+        CaptureTime {
+            local: at(10, 0, 0),
+            offset: None,
+            source: "ExifIFD:DateTimeOriginal".to_string(),
+            origin,
+            sub_sec: None,
+            original: None,
+        }
+    }
+
+    fn canon() -> MediaMetadata {
+        MediaMetadata {
+            make: Some("Canon".to_string()),
+            model: Some("Canon EOS 80D".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_deltas() {
+        assert_eq!(parse_delta("+1h03m"), Some(Duration::seconds(3780)));
+        assert_eq!(parse_delta("-2d"), Some(Duration::seconds(-172_800)));
+        assert_eq!(parse_delta("+45s"), Some(Duration::seconds(45)));
+        assert_eq!(
+            parse_delta("-1d2h30s"),
+            Some(Duration::seconds(-(86_400 + 7_200 + 30)))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_deltas() {
+        for s in ["1h", "+", "+h", "+1", "+1x", "+1h2", ""] {
+            assert_eq!(parse_delta(s), None, "{:?}", s);
+        }
+        assert_eq!(parse_delta("+99999999999999999999d"), None);
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rule = ShiftRule::parse("Canon EOS 80D=+1h03m").unwrap();
+        assert!(matches!(rule.target, ShiftTarget::Camera(ref c) if c == "Canon EOS 80D"));
+        assert_eq!(rule.delta, Duration::seconds(3780));
+
+        let rule = ShiftRule::parse("tag: Mariage XYZ =-1d").unwrap();
+        assert!(matches!(rule.target, ShiftTarget::Tag(ref t) if t == "Mariage XYZ"));
+
+        for bad in ["Canon EOS 80D", "=+1h", "tag:=+1h", "Canon=1h"] {
+            assert!(ShiftRule::parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn matches_camera_and_tag() {
+        let meta = canon();
+        let model = ShiftRule::parse("canon eos 80d=+1h").unwrap();
+        assert!(model.matches(None, Some(&meta)));
+        assert!(!model.matches(None, None));
+
+        let tag = ShiftRule::parse("tag:Mariage=+1h").unwrap();
+        assert!(tag.matches(Some("Mariage"), None));
+        assert!(!tag.matches(Some("Vacances"), Some(&meta)));
+    }
+
+    #[test]
+    fn shifts_metadata_dates() {
+        let rules = [ShiftRule::parse("Canon EOS 80D=+1h03m").unwrap()];
+        let meta = canon();
+        let shifted = apply_shift(
+            Path::new("a.jpg"),
+            &rules,
+            capture(DateOrigin::Metadata),
+            None,
+            Some(&meta),
+        );
+        assert_eq!(shifted.local, at(11, 3, 0));
+        assert_eq!(shifted.original, Some(at(10, 0, 0)));
+    }
+
+    #[test]
+    fn leaves_other_date_origins_alone() {
+        let rules = [ShiftRule::parse("tag:Voyage=+1h").unwrap()];
+        for origin in [DateOrigin::Filename, DateOrigin::Folder, DateOrigin::Mtime] {
+            let c = apply_shift(
+                Path::new("a.jpg"),
+                &rules,
+                capture(origin),
+                Some("Voyage"),
+                None,
+            );
+            assert_eq!(c.local, at(10, 0, 0));
+            assert_eq!(c.original, None);
+        }
+    }
+}
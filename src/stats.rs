@@ -1,13 +1,22 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-/// Statistiques de traitement
+use crate::metadata::DateOrigin;
+
+/// Statistiques de traitement, en nombre de fichiers (membres des groupes compris)
 #[derive(Debug, Clone)]
 pub struct Stats {
     pub processed: Arc<AtomicUsize>,
     pub duplicates: Arc<AtomicUsize>,
     pub errors: Arc<AtomicUsize>,
     pub renamed: Arc<AtomicUsize>,
+    pub near_duplicates: Arc<AtomicUsize>,
+    /// Provenance des dates: métadonnées, nom de fichier, dossier, mtime, aucune
+    pub dates_metadata: Arc<AtomicUsize>,
+    pub dates_filename: Arc<AtomicUsize>,
+    pub dates_folder: Arc<AtomicUsize>,
+    pub dates_mtime: Arc<AtomicUsize>,
+    pub undated: Arc<AtomicUsize>,
 }
 
 impl Stats {
@@ -18,6 +27,12 @@ impl Stats {
             duplicates: Arc::new(AtomicUsize::new(0)),
             errors: Arc::new(AtomicUsize::new(0)),
             renamed: Arc::new(AtomicUsize::new(0)),
+            near_duplicates: Arc::new(AtomicUsize::new(0)),
+            dates_metadata: Arc::new(AtomicUsize::new(0)),
+            dates_filename: Arc::new(AtomicUsize::new(0)),
+            dates_folder: Arc::new(AtomicUsize::new(0)),
+            dates_mtime: Arc::new(AtomicUsize::new(0)),
+            undated: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -26,14 +41,24 @@ impl Stats {
         self.processed.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Nombre de fichiers traités jusqu'ici
+    pub fn processed(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    /// Nombre de fichiers en erreur jusqu'ici
+    pub fn errors(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+
     /// Incrémente le compteur de doublons
     pub fn inc_duplicates(&self) {
         self.duplicates.fetch_add(1, Ordering::Relaxed);
     }
 
-    /// Incrémente le compteur d'erreurs
-    pub fn inc_errors(&self) {
-        self.errors.fetch_add(1, Ordering::Relaxed);
+    /// Compte `files` fichiers en erreur (tout un groupe échoue ensemble)
+    pub fn add_errors(&self, files: usize) {
+        self.errors.fetch_add(files, Ordering::Relaxed);
     }
 
     /// Incrémente le compteur de fichiers renommés (collision de hash)
@@ -41,17 +66,44 @@ impl Stats {
         self.renamed.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Compte `files` fichiers quasi-doublons (`--near-dup`): le groupe de l'image
+    pub fn add_near_duplicates(&self, files: usize) {
+        self.near_duplicates.fetch_add(files, Ordering::Relaxed);
+    }
+
+    /// Compte la provenance de la date de `files` fichiers (None: sans date)
+    pub fn add_date_origin(&self, origin: Option<DateOrigin>, files: usize) {
+        let counter = match origin {
+            Some(DateOrigin::Metadata) => &self.dates_metadata,
+            Some(DateOrigin::Filename) => &self.dates_filename,
+            Some(DateOrigin::Folder) => &self.dates_folder,
+            Some(DateOrigin::Mtime) => &self.dates_mtime,
+            None => &self.undated,
+        };
+        counter.fetch_add(files, Ordering::Relaxed);
+    }
+
     /// Affiche un résumé des statistiques
     pub fn print_summary(&self) {
         let processed = self.processed.load(Ordering::Relaxed);
         let duplicates = self.duplicates.load(Ordering::Relaxed);
         let errors = self.errors.load(Ordering::Relaxed);
         let renamed = self.renamed.load(Ordering::Relaxed);
+        let near_duplicates = self.near_duplicates.load(Ordering::Relaxed);
 
         println!("\n=== Summary ===");
         println!("Files processed: {}", processed);
         println!("Duplicates skipped: {}", duplicates);
         println!("Files renamed (hash collision): {}", renamed);
+        println!("Near-duplicates: {}", near_duplicates);
+        println!(
+            "Date sources: metadata {}, filename {}, folder {}, mtime {}, none {}",
+            self.dates_metadata.load(Ordering::Relaxed),
+            self.dates_filename.load(Ordering::Relaxed),
+            self.dates_folder.load(Ordering::Relaxed),
+            self.dates_mtime.load(Ordering::Relaxed),
+            self.undated.load(Ordering::Relaxed)
+        );
         println!("Errors: {}", errors);
     }
 }
@@ -0,0 +1,459 @@
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use std::fmt;
+
+use crate::filesystem::hash_prefix;
+
+/// Longueur par défaut de `{hash}`
+const DEFAULT_HASH_LEN: usize = 8;
+/// Nombre de chiffres par défaut de `{subsec}` (millisecondes)
+const DEFAULT_SUBSEC_DIGITS: usize = 3;
+
+/// Placeholders reconnus (pour les messages d'erreur)
+const PLACEHOLDERS: &[&str] = &[
+    "year", "month", "day", "hour", "minute", "second", "subsec", "subsec:N", "tag", "make",
+    "model", "kind", "stem", "hash", "hash:N", "counter",
+];
+
+/// Valeur substituée à un placeholder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    /// Fraction de seconde sur N chiffres
+    SubSec(usize),
+    Tag,
+    Make,
+    Model,
+    Kind,
+    Stem,
+    Hash(usize),
+    Counter,
+}
+
+impl Field {
+    /// Indique si le placeholder a une valeur pour tout fichier (date, nom d'origine, hash)
+    fn always_present(self) -> bool {
+        matches!(
+            self,
+            Field::Year
+                | Field::Month
+                | Field::Day
+                | Field::Hour
+                | Field::Minute
+                | Field::Second
+                | Field::Stem
+                | Field::Hash(_)
+        )
+    }
+}
+
+/// Élément d'un template
+#[derive(Debug, Clone)]
+enum Part {
+    Literal(String),
+    Field(Field),
+    /// Section `[...]`, omise si l'un de ses placeholders est vide
+    Optional(Vec<Part>),
+}
+
+/// Informations disponibles pour construire un chemin ou un nom de fichier
+#[derive(Debug, Clone, Default)]
+pub struct NamingContext<'a> {
+    pub dt: NaiveDateTime,
+    /// Fraction de seconde lue dans les métadonnées (nanosecondes), None si inconnue
+    pub sub_sec: Option<u32>,
+    pub tag: Option<&'a str>,
+    pub make: Option<&'a str>,
+    pub model: Option<&'a str>,
+    /// Type de média (photo, video…)
+    pub kind: &'a str,
+    /// Nom du fichier d'origine, sans extension
+    pub stem: &'a str,
+    pub hash: Option<&'a blake3::Hash>,
+    /// Compteur de collision (None pour le premier nom essayé)
+    pub counter: Option<u32>,
+}
+
+/// Template de chemin (`--layout`) ou de nom de fichier (`--name`).
+///
+/// Syntaxe: texte libre, placeholders `{year}`, `{month}`, `{day}`, `{hour}`,
+/// `{minute}`, `{second}`, `{subsec}` / `{subsec:N}`, `{tag}`, `{make}`, `{model}`, `{kind}`, `{stem}`,
+/// `{hash}` / `{hash:N}` et `{counter}`, sections optionnelles `[...]` omises
+/// dès qu'un des placeholders qu'elles contiennent est vide.
+#[derive(Clone)]
+pub struct Template {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Template({:?})", self.source)
+    }
+}
+
+impl Template {
+    /// Parse un template de dossier (`--layout`), relatif à la racine de sortie
+    ///
+    /// # Errors
+    /// Retourne une erreur si la syntaxe est invalide, si le chemin est absolu
+    /// ou s'il contient un composant `..`
+    pub fn parse_layout(source: &str) -> Result<Self> {
+        let t = Self::parse(source)?;
+        if source.starts_with('/') || source.starts_with('\\') {
+            bail!("--layout must be relative to --output: {:?}", source);
+        }
+        if source.split(['/', '\\']).any(|c| c.trim() == "..") {
+            bail!("--layout must not contain '..': {:?}", source);
+        }
+        Ok(t)
+    }
+
+    /// Parse un template de nom de fichier (`--name`), sans extension
+    ///
+    /// # Errors
+    /// Retourne une erreur si la syntaxe est invalide, si le template est vide,
+    /// s'il contient un séparateur de chemin, ou si aucun placeholder toujours
+    /// renseigné (date, `{stem}`, `{hash}`) n'est hors des sections optionnelles:
+    /// le nom pourrait être vide (fichier `.jpg`)
+    pub fn parse_name(source: &str) -> Result<Self> {
+        let t = Self::parse(source)?;
+        if t.parts.is_empty() {
+            bail!("--name must not be empty");
+        }
+        if source.contains(['/', '\\']) {
+            bail!("--name must not contain path separators: {:?}", source);
+        }
+        let named = t
+            .parts
+            .iter()
+            .any(|p| matches!(p, Part::Field(f) if f.always_present()));
+        if !named {
+            bail!(
+                "--name needs a date part, {{stem}} or {{hash}} outside of [...]: {:?}",
+                source
+            );
+        }
+        Ok(t)
+    }
+
+    fn parse(source: &str) -> Result<Self> {
+        let mut chars = source.chars().peekable();
+        let parts = parse_parts(&mut chars, source, false)?;
+        Ok(Self {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    /// Indique si le template utilise `{counter}`
+    pub fn uses_counter(&self) -> bool {
+        contains_field(&self.parts, |f| f == Field::Counter)
+    }
+
+    /// Produit le texte du template pour un fichier donné
+    pub fn render(&self, ctx: &NamingContext) -> String {
+        let mut out = String::new();
+        render_parts(&self.parts, ctx, &mut out);
+        out
+    }
+}
+
+/// Parse une suite d'éléments, jusqu'à la fin ou jusqu'au `]` fermant
+fn parse_parts(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    source: &str,
+    in_optional: bool,
+) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => bail!("unclosed '{{' in template {:?}", source),
+                    }
+                }
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Field(parse_field(&name, source)?));
+            }
+            '[' => {
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Optional(parse_parts(chars, source, true)?));
+            }
+            ']' if in_optional => {
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(literal));
+                }
+                return Ok(parts);
+            }
+            '}' | ']' => bail!("unexpected '{}' in template {:?}", c, source),
+            _ => literal.push(c),
+        }
+    }
+
+    if in_optional {
+        bail!("unclosed '[' in template {:?}", source);
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    Ok(parts)
+}
+
+/// Parse le nom d'un placeholder
+fn parse_field(name: &str, source: &str) -> Result<Field> {
+    let field = match name.trim() {
+        "year" => Field::Year,
+        "month" => Field::Month,
+        "day" => Field::Day,
+        "hour" => Field::Hour,
+        "minute" => Field::Minute,
+        "second" => Field::Second,
+        "tag" => Field::Tag,
+        "make" => Field::Make,
+        "model" => Field::Model,
+        "kind" => Field::Kind,
+        "stem" => Field::Stem,
+        "counter" => Field::Counter,
+        "hash" => Field::Hash(DEFAULT_HASH_LEN),
+        "subsec" => Field::SubSec(DEFAULT_SUBSEC_DIGITS),
+        other => match other.split_once(':') {
+            Some(("hash", n)) => match n.parse::<usize>() {
+                Ok(n) if (4..=64).contains(&n) => Field::Hash(n),
+                _ => bail!(
+                    "invalid {{{}}} in template {:?}: length must be 4..=64",
+                    other,
+                    source
+                ),
+            },
+            Some(("subsec", n)) => match n.parse::<usize>() {
+                Ok(n) if (1..=9).contains(&n) => Field::SubSec(n),
+                _ => bail!(
+                    "invalid {{{}}} in template {:?}: digits must be 1..=9",
+                    other,
+                    source
+                ),
+            },
+            _ => bail!(
+                "unknown placeholder {{{}}} in template {:?} (expected one of: {})",
+                other,
+                source,
+                PLACEHOLDERS.join(", ")
+            ),
+        },
+    };
+    Ok(field)
+}
+
+fn contains_field(parts: &[Part], pred: impl Fn(Field) -> bool + Copy) -> bool {
+    parts.iter().any(|p| match p {
+        Part::Field(f) => pred(*f),
+        Part::Optional(inner) => contains_field(inner, pred),
+        Part::Literal(_) => false,
+    })
+}
+
+/// Valeur d'un placeholder (None si elle est absente pour ce fichier)
+fn field_value(field: Field, ctx: &NamingContext) -> Option<String> {
+    let dt = &ctx.dt;
+    let value = match field {
+        Field::Year => format!("{:04}", dt.year()),
+        Field::Month => format!("{:02}", dt.month()),
+        Field::Day => format!("{:02}", dt.day()),
+        Field::Hour => format!("{:02}", dt.hour()),
+        Field::Minute => format!("{:02}", dt.minute()),
+        Field::Second => format!("{:02}", dt.second()),
+        // Vide si la date n'a pas de fraction de seconde connue (`.000` si elle est nulle)
+        Field::SubSec(n) => {
+            let nanos = ctx.sub_sec?;
+            format!("{:0width$}", nanos / 10u32.pow(9 - n as u32), width = n)
+        }
+        Field::Tag => sanitize(ctx.tag?),
+        Field::Make => sanitize(ctx.make?),
+        Field::Model => sanitize(ctx.model?),
+        Field::Kind => ctx.kind.to_string(),
+        // Un nom fait seulement de points ou d'espaces (`....jpg`) donnerait un
+        // fichier caché: le hash le remplace
+        Field::Stem => match sanitize(ctx.stem) {
+            stem if stem.is_empty() => hash_prefix(ctx.hash?, DEFAULT_HASH_LEN),
+            stem => stem,
+        },
+        Field::Hash(n) => hash_prefix(ctx.hash?, n),
+        Field::Counter => ctx.counter?.to_string(),
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+/// Rend les éléments; retourne false si un placeholder était vide
+fn render_parts(parts: &[Part], ctx: &NamingContext, out: &mut String) -> bool {
+    let mut complete = true;
+    for part in parts {
+        match part {
+            Part::Literal(s) => out.push_str(s),
+            Part::Field(f) => match field_value(*f, ctx) {
+                Some(v) => out.push_str(&v),
+                None => complete = false,
+            },
+            Part::Optional(inner) => {
+                let mut section = String::new();
+                if render_parts(inner, ctx, &mut section) {
+                    out.push_str(&section);
+                }
+            }
+        }
+    }
+    complete
+}
+
+/// Rend une valeur utilisable dans un nom de fichier (pas de séparateur ni caractère de contrôle)
+fn sanitize(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect::<String>()
+        .trim_matches('.')
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Timelike};
+
+    fn ctx() -> NamingContext<'static> {
+        NamingContext {
+            dt: NaiveDate::from_ymd_opt(2025, 7, 23)
+                .unwrap()
+                .and_hms_opt(8, 54, 4)
+                .unwrap(),
+            kind: "photo",
+            stem: "IMG_0001",
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_default_name() {
+        let t = Template::parse_name("{year}-{month}-{day} {hour}.{minute}.{second}").unwrap();
+        assert_eq!(t.render(&ctx()), "2025-07-23 08.54.04");
+    }
+
+    #[test]
+    fn optional_section_is_dropped_when_a_field_is_empty() {
+        let t = Template::parse_layout("{year}/{month}[ {tag}]").unwrap();
+        assert_eq!(t.render(&ctx()), "2025/07");
+
+        let with_tag = NamingContext {
+            tag: Some("Mariage XYZ"),
+            ..ctx()
+        };
+        assert_eq!(t.render(&with_tag), "2025/07 Mariage XYZ");
+    }
+
+    #[test]
+    fn field_values_are_sanitized() {
+        let t = Template::parse_name("{stem} {tag}").unwrap();
+        let c = NamingContext {
+            tag: Some("a/b:c"),
+            ..ctx()
+        };
+        assert_eq!(t.render(&c), "IMG_0001 a-b-c");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        assert!(Template::parse_name("{unknown}").is_err());
+        assert!(Template::parse_name("{hash:3}").is_err());
+        assert!(Template::parse_name("{year").is_err());
+        assert!(Template::parse_name("[{year}").is_err());
+        assert!(Template::parse_name("").is_err());
+        assert!(Template::parse_name("{year}/{month}").is_err());
+        assert!(Template::parse_name("photo").is_err());
+        assert!(Template::parse_layout("/{year}").is_err());
+        assert!(Template::parse_layout("{year}/../x").is_err());
+    }
+
+    #[test]
+    fn rejects_names_that_can_be_empty() {
+        for source in [
+            "[{make}]",
+            "{make}",
+            "{tag}[ {model}]",
+            "{counter}",
+            "[{year}]",
+        ] {
+            let err = Template::parse_name(source).unwrap_err();
+            assert!(err.to_string().contains("outside of [...]"), "{}", source);
+        }
+        for source in ["{stem}", "[{make} ]{hash:12}", "{year}[ {tag}]"] {
+            assert!(Template::parse_name(source).is_ok(), "{}", source);
+        }
+        // Les dossiers peuvent être vides (fichiers à la racine de --output)
+        assert!(Template::parse_layout("[{tag}]").is_ok());
+    }
+
+    #[test]
+    fn empty_stem_falls_back_to_the_hash() {
+        let hash = blake3::hash(b"photo");
+        let t = Template::parse_name("{stem}").unwrap();
+        for stem in ["...", " . ", ""] {
+            let ctx = NamingContext {
+                stem,
+                hash: Some(&hash),
+                ..ctx()
+            };
+            assert_eq!(
+                t.render(&ctx),
+                hash_prefix(&hash, DEFAULT_HASH_LEN),
+                "{:?}",
+                stem
+            );
+        }
+    }
+
+    #[test]
+    fn detects_counter() {
+        assert!(Template::parse_name("{stem}[-{counter}]")
+            .unwrap()
+            .uses_counter());
+        assert!(!Template::parse_name("{stem}").unwrap().uses_counter());
+    }
+
+    #[test]
+    fn subsec_is_rendered_only_when_known() {
+        let at = |sub_sec: Option<u32>| NamingContext {
+            dt: ctx().dt.with_nanosecond(sub_sec.unwrap_or(0)).unwrap(),
+            sub_sec,
+            ..ctx()
+        };
+        let t = Template::parse_name("{second}[.{subsec}]").unwrap();
+        assert_eq!(t.render(&at(None)), "04");
+        assert_eq!(t.render(&at(Some(0))), "04.000");
+        assert_eq!(t.render(&at(Some(120_000_000))), "04.120");
+
+        let t = Template::parse_name("{second}.{subsec:2}").unwrap();
+        assert_eq!(t.render(&at(Some(187_000_000))), "04.18");
+        assert!(Template::parse_name("{subsec:0}").is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{bail, Context, Result};
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+use crate::filesystem::files_open_for_writing;
+
+/// Intervalle de vérification des fichiers en attente
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Actions déclenchées par le watcher
+pub trait WatchHandler {
+    /// Indique si un fichier du dépôt doit être classé
+    fn accepts(&self, path: &Path) -> bool;
+
+    /// Classe des fichiers devenus stables
+    fn on_ready(&mut self, files: Vec<PathBuf>);
+
+    /// Appelé une fois après chaque période d'inactivité (nettoyage)
+    fn on_quiet(&mut self);
+}
+
+/// Fichier en attente de stabilité
+struct Pending {
+    size: u64,
+    mtime: Option<SystemTime>,
+    /// Dernier changement observé
+    since: Instant,
+}
+
+/// Surveille un dossier (inotify sous Linux) et transmet les fichiers stables.
+///
+/// Un fichier est stable quand sa taille et sa date de modification n'ont pas
+/// changé depuis `settle` et qu'aucun processus ne l'a ouvert en écriture. Les
+/// fichiers d'un même dossier sont transmis ensemble, une fois tous stables,
+/// pour qu'un média et ses compagnons restent groupés.
+///
+/// # Arguments
+/// * `input` - Dossier surveillé (récursivement)
+/// * `settle` - Durée sans changement avant qu'un fichier soit classé
+/// * `quiet` - Durée sans activité avant l'appel de `on_quiet`
+/// * `handler` - Actions à déclencher
+///
+/// # Errors
+/// Retourne une erreur si la surveillance ne peut pas démarrer ou s'interrompt
+pub fn run(
+    input: &Path,
+    settle: Duration,
+    quiet: Duration,
+    handler: &mut impl WatchHandler,
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Unable to start file watcher")?;
+    watcher
+        .watch(input, RecursiveMode::Recursive)
+        .with_context(|| format!("Unable to watch {}", input.display()))?;
+
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    let mut last_activity = Instant::now();
+    let mut last_check = Instant::now();
+    let mut quiet_done = true;
+
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if is_content_change(&event.kind) {
+                    let mut tracked = false;
+                    for path in &event.paths {
+                        tracked |= track(&mut pending, path, handler);
+                    }
+                    if tracked {
+                        last_activity = Instant::now();
+                        quiet_done = false;
+                    }
+                }
+            }
+            Ok(Err(e)) => log::warn!("[WATCH] {}", e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => bail!("file watcher stopped"),
+        }
+
+        if last_check.elapsed() < POLL_INTERVAL {
+            continue;
+        }
+        last_check = Instant::now();
+
+        let ready = take_ready(&mut pending, settle);
+        if !ready.is_empty() {
+            handler.on_ready(ready);
+            last_activity = Instant::now();
+            quiet_done = false;
+        }
+
+        if !quiet_done && pending.is_empty() && last_activity.elapsed() >= quiet {
+            handler.on_quiet();
+            quiet_done = true;
+        }
+    }
+}
+
+/// Indique si un évènement peut signaler un fichier nouveau ou modifié.
+///
+/// Les changements de métadonnées (droits, nombre de liens…) sont ignorés: le
+/// `link()` de `--mode hardlink` en provoque un sur chaque fichier classé, qui serait
+/// sinon re-classé comme doublon.
+fn is_content_change(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Create(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        _ => false,
+    }
+}
+
+/// Suit un fichier signalé (ou tous les fichiers d'un dossier déplacé dans le dépôt)
+///
+/// # Returns
+/// true si au moins un fichier est suivi
+fn track(
+    pending: &mut HashMap<PathBuf, Pending>,
+    path: &Path,
+    handler: &impl WatchHandler,
+) -> bool {
+    if path.is_dir() {
+        let mut tracked = false;
+        for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
+            if entry.file_type().is_file() {
+                tracked |= track(pending, entry.path(), handler);
+            }
+        }
+        return tracked;
+    }
+
+    if !handler.accepts(path) {
+        return false;
+    }
+    let Ok(md) = fs::metadata(path) else {
+        return false;
+    };
+    pending.insert(
+        path.to_path_buf(),
+        Pending {
+            size: md.len(),
+            mtime: md.modified().ok(),
+            since: Instant::now(),
+        },
+    );
+    true
+}
+
+/// Retire des fichiers en attente ceux qui sont prêts à être classés
+fn take_ready(pending: &mut HashMap<PathBuf, Pending>, settle: Duration) -> Vec<PathBuf> {
+    // Mise à jour: fichier disparu (renommé, supprimé) ou encore modifié
+    pending.retain(|path, p| {
+        let Ok(md) = fs::metadata(path) else {
+            return false;
+        };
+        let mtime = md.modified().ok();
+        if md.len() != p.size || mtime != p.mtime {
+            p.size = md.len();
+            p.mtime = mtime;
+            p.since = Instant::now();
+        }
+        true
+    });
+
+    let settled = |p: &Pending| p.since.elapsed() >= settle;
+    if !pending.values().any(settled) {
+        return Vec::new();
+    }
+
+    let writing = files_open_for_writing();
+    let is_stable = |path: &PathBuf, p: &Pending| settled(p) && !writing.contains(path);
+
+    // Un dossier n'est transmis que lorsque tous ses fichiers en attente sont stables
+    let busy_dirs: HashSet<&Path> = pending
+        .iter()
+        .filter(|(path, p)| !is_stable(path, p))
+        .filter_map(|(path, _)| path.parent())
+        .collect();
+    let mut ready: Vec<PathBuf> = pending
+        .keys()
+        .filter(|path| path.parent().is_some_and(|d| !busy_dirs.contains(d)))
+        .cloned()
+        .collect();
+    ready.sort();
+
+    for path in &ready {
+        pending.remove(path);
+    }
+    ready
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepte les fichiers `.jpg`
+    struct Jpegs;
+
+    impl WatchHandler for Jpegs {
+        fn accepts(&self, path: &Path) -> bool {
+            path.extension().is_some_and(|e| e == "jpg")
+        }
+
+        fn on_ready(&mut self, _files: Vec<PathBuf>) {}
+
+        fn on_quiet(&mut self) {}
+    }
+
+    const SETTLE: Duration = Duration::from_secs(5);
+
+    fn write(path: &Path, content: &[u8]) -> PathBuf {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+        path.to_path_buf()
+    }
+
+    /// Fait comme si le fichier n'avait pas changé depuis `SETTLE`
+    fn settle(pending: &mut HashMap<PathBuf, Pending>, path: &Path) {
+        pending.get_mut(path).unwrap().since = Instant::now() - SETTLE;
+    }
+
+    #[test]
+    fn metadata_changes_are_ignored() {
+        use notify::event::{CreateKind, DataChange, MetadataKind, RemoveKind, RenameMode};
+
+        assert!(is_content_change(&EventKind::Create(CreateKind::File)));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Data(
+            DataChange::Any
+        ))));
+        assert!(is_content_change(&EventKind::Modify(ModifyKind::Name(
+            RenameMode::To
+        ))));
+        // IN_ATTRIB: le lien dur créé par le classement lui-même
+        assert!(!is_content_change(&EventKind::Modify(
+            ModifyKind::Metadata(MetadataKind::Any)
+        )));
+        assert!(!is_content_change(&EventKind::Remove(RemoveKind::File)));
+    }
+
+    #[test]
+    fn files_wait_for_the_settle_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let photo = write(&dir.path().join("IMG_0001.jpg"), b"photo");
+        let mut pending = HashMap::new();
+        assert!(track(&mut pending, &photo, &Jpegs));
+        assert!(!track(
+            &mut pending,
+            &write(&dir.path().join("notes.txt"), b""),
+            &Jpegs
+        ));
+
+        assert!(take_ready(&mut pending, SETTLE).is_empty());
+        assert!(pending.contains_key(&photo));
+
+        // Modifié pendant l'attente: la fenêtre repart de zéro
+        settle(&mut pending, &photo);
+        write(&photo, b"photo, complete");
+        assert!(take_ready(&mut pending, SETTLE).is_empty());
+
+        settle(&mut pending, &photo);
+        assert_eq!(take_ready(&mut pending, SETTLE), [photo]);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn busy_file_holds_back_its_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let done = write(&dir.path().join("a/IMG_0001.jpg"), b"done");
+        let busy = write(&dir.path().join("a/IMG_0002.jpg"), b"busy");
+        let other = write(&dir.path().join("b/IMG_0003.jpg"), b"other");
+        let mut pending = HashMap::new();
+        for path in [&done, &busy, &other] {
+            track(&mut pending, path, &Jpegs);
+        }
+
+        settle(&mut pending, &done);
+        settle(&mut pending, &other);
+        assert_eq!(take_ready(&mut pending, SETTLE), [other]);
+        assert!(pending.contains_key(&done));
+
+        settle(&mut pending, &busy);
+        assert_eq!(take_ready(&mut pending, SETTLE), [done, busy]);
+    }
+
+    #[test]
+    fn moved_in_directory_tracks_its_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let trip = dir.path().join("trip");
+        let photos = [
+            write(&trip.join("IMG_0001.jpg"), b"1"),
+            write(&trip.join("day 2/IMG_0002.jpg"), b"2"),
+        ];
+        write(&trip.join("notes.txt"), b"");
+        let mut pending = HashMap::new();
+        assert!(track(&mut pending, &trip, &Jpegs));
+
+        let mut tracked: Vec<&PathBuf> = pending.keys().collect();
+        tracked.sort();
+        assert_eq!(tracked, [&photos[0], &photos[1]]);
+
+        // Dossier sans fichier accepté
+        let docs = dir.path().join("docs");
+        write(&docs.join("notes.txt"), b"");
+        assert!(!track(&mut pending, &docs, &Jpegs));
+
+        // Fichier disparu avant d'être stable: oublié
+        fs::remove_file(&photos[1]).unwrap();
+        for path in &photos {
+            settle(&mut pending, path);
+        }
+        assert_eq!(take_ready(&mut pending, SETTLE), [photos[0].clone()]);
+        assert!(pending.is_empty());
+    }
+}